@@ -1,39 +1,91 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{collections::BTreeMap, fmt::Debug, sync::Arc};
 
-use said::derivation::HashFunctionCode;
+use chrono::{DateTime, SecondsFormat, Utc};
+use said::derivation::{HashFunction, HashFunctionCode};
 use said::sad::sad_macros::SAD;
 use said::sad::{SerializationFormats, SAD};
 use said::SelfAddressingIdentifier;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::{error::Error, seals::Seal, verifier::Verifier, Encode, Identifier};
+use crate::{
+    canonical,
+    config::LedgerConfig,
+    error::Error,
+    limits::Limits,
+    receipt::Receipt,
+    seals::Seal,
+    verifier::{first_failure, SignatureStatus, Verifier},
+    version::{SerializationKind, Version, VersionError},
+    Encode, Identifier,
+};
 use crate::{Result, Signature};
 
 #[derive(Error, Debug)]
 pub enum BlockError {
     #[error("Incorect previous block binding")]
     WrongBlockBinding,
+    #[error("Improper timestamp: {0}")]
+    WrongTimestamp(String),
+    #[error("Validity window ends before it starts")]
+    WrongValidityWindow,
+    #[error("Block has no signatures")]
+    MissingSignatures,
+    #[error("None of block signatures identifies its signer")]
+    UnattributableSignatures,
+    #[error("Block is signed more than once by the same identifier")]
+    DuplicatedSigner,
+    #[error("Signature index {0} doesn't point to proper controlling identifier")]
+    WrongSignatureIndex(usize),
+    #[error("Block can't be endorsed by its controlling identifier")]
+    EndorsedByController,
 }
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, Debug, SAD)]
 pub struct Block<I: Identifier + Serialize + Clone> {
+    /// Version string. Blocks created before versioning was introduced
+    /// don't have it.
+    #[serde(rename = "v", default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<Version>,
     #[serde(rename = "ci")]
     pub controlling_identifiers: Vec<I>,
     #[serde(rename = "d")]
     #[said]
     pub digital_fingerprint: Option<SelfAddressingIdentifier>,
+    /// Identifier of microledger the block belongs to, ie. fingerprint of its
+    /// genesis block. Genesis block itself doesn't carry it.
+    #[serde(rename = "i", default, skip_serializing_if = "Option::is_none")]
+    pub ledger_id: Option<SelfAddressingIdentifier>,
+    /// Position of the block in microledger, starting from 0 for genesis block.
+    #[serde(rename = "sn", default, skip_serializing_if = "Option::is_none")]
+    pub sequence_number: Option<u64>,
+    /// Time of block creation in RFC 3339 format.
+    #[serde(rename = "dt", default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
+    /// Start of block validity window in RFC 3339 format.
+    #[serde(rename = "nb", default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<String>,
+    /// End of block validity window in RFC 3339 format.
+    #[serde(rename = "na", default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<String>,
     #[serde(rename = "p", skip_serializing_if = "Option::is_none")]
     pub previous: Option<SelfAddressingIdentifier>,
     #[serde(rename = "s")]
     pub seals: Vec<Seal>,
+    /// Additional header parameters, ie. schema identifiers or tags.
+    #[serde(rename = "md", default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, serde_json::Value>,
+    /// Ledger configuration. Set in genesis block, or in later block to amend it.
+    #[serde(rename = "cf", default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<LedgerConfig>,
+    /// Terminal block closes the microledger. No block can be anchored after it.
+    #[serde(rename = "t", default, skip_serializing_if = "std::ops::Not::not")]
+    pub terminal: bool,
 }
 
 impl<I: Identifier + Serialize + Clone> Encode for Block<I> {
     fn encode(&self) -> Result<Vec<u8>> {
-        serde_json::to_string(self)
-            .map(|encoded| encoded.as_bytes().to_vec())
-            .map_err(Error::EncodeError)
+        self.serialization_kind().encode(self)
     }
 }
 
@@ -42,14 +94,238 @@ impl<I: Identifier + Serialize + Clone> Block<I> {
         seals: Vec<Seal>,
         previous: Option<SelfAddressingIdentifier>,
         controlling_identifiers: Vec<I>,
-    ) -> Self {
+    ) -> Result<Self> {
         let new_block = Self {
+            version: Some(Version::new(SerializationKind::Json, 0)),
             digital_fingerprint: None,
+            ledger_id: None,
+            sequence_number: None,
+            timestamp: None,
+            not_before: None,
+            not_after: None,
             seals,
             previous,
             controlling_identifiers,
+            metadata: BTreeMap::new(),
+            config: None,
+            terminal: false,
         };
-        new_block.compute_digest(HashFunctionCode::Blake3_256, SerializationFormats::JSON)
+        new_block.digest()
+    }
+
+    /// Creates block that terminates the microledger. Terminal block has no
+    /// controlling identifiers, so nobody can anchor anything after it.
+    pub fn new_terminal(
+        seals: Vec<Seal>,
+        previous: Option<SelfAddressingIdentifier>,
+    ) -> Result<Self> {
+        let new_block = Self {
+            terminal: true,
+            ..Self::new(seals, previous, vec![])?
+        };
+        new_block.digest()
+    }
+
+    /// Serialization kind declared in version string. Blocks without version
+    /// string are serialized as JSON.
+    pub fn serialization_kind(&self) -> SerializationKind {
+        self.version
+            .as_ref()
+            .map(|version| version.kind)
+            .unwrap_or(SerializationKind::Json)
+    }
+
+    /// Sets serialization kind of the block and recomputes its fingerprint
+    /// in that serialization.
+    pub fn with_serialization_kind(self, kind: SerializationKind) -> Result<Self> {
+        let new_block = Self {
+            version: Some(Version::new(kind, 0)),
+            ..self
+        };
+        new_block.digest()
+    }
+
+    /// Computes block fingerprint using given digest algorithm. Subsequent
+    /// changes of the block keep using it.
+    pub fn with_hash_algorithm(self, code: HashFunctionCode) -> Result<Self> {
+        self.digest_with(code)
+    }
+
+    /// Digest algorithm of block fingerprint, as recorded in its derivation
+    /// code. Blake3-256 is used for blocks without fingerprint.
+    pub fn hash_algorithm(&self) -> HashFunctionCode {
+        self.digital_fingerprint
+            .as_ref()
+            .map(|digest| HashFunctionCode::from(&digest.derivation))
+            .unwrap_or(HashFunctionCode::Blake3_256)
+    }
+
+    /// Computes block fingerprint. Block size declared in version string is
+    /// updated first, so it matches the final serialization.
+    pub(crate) fn digest(self) -> Result<Self> {
+        let code = self.hash_algorithm();
+        self.digest_with(code)
+    }
+
+    fn digest_with(self, code: HashFunctionCode) -> Result<Self> {
+        let digested = self.compute_said(code.clone())?;
+        match digested.version.clone() {
+            Some(version) => {
+                let size = digested.encode()?.len();
+                let new_block = Self {
+                    version: Some(version.with_size(size)?),
+                    ..digested
+                };
+                new_block.compute_said(code)
+            }
+            None => Ok(digested),
+        }
+    }
+
+    /// Checks if version string is supported and declares actual size of
+    /// the block. Blocks without version string aren't checked.
+    pub fn check_version(&self) -> Result<()> {
+        match self.version {
+            Some(ref version) => version.check(self.encode()?.len()),
+            None => Ok(()),
+        }
+    }
+
+    /// Computes self-addressing identifier in block serialization kind.
+    fn compute_said(self, code: HashFunctionCode) -> Result<Self> {
+        let data = self.fingerprint_data(&code)?;
+        Ok(Self {
+            digital_fingerprint: Some(HashFunction::from(code).derive(&data)),
+            ..self
+        })
+    }
+
+    /// Data the fingerprint is computed over: block in its serialization
+    /// kind, with fingerprint replaced by placeholder put by `said`. `said`
+    /// doesn't support canonical JSON and serializes MessagePack without
+    /// field names, so these are transcoded from its JSON derivation data.
+    fn fingerprint_data(&self, code: &HashFunctionCode) -> Result<Vec<u8>> {
+        let json = || self.derivation_data(code, &SerializationFormats::JSON);
+        Ok(match self.serialization_kind() {
+            SerializationKind::Json => json(),
+            SerializationKind::Cbor => self.derivation_data(code, &SerializationFormats::CBOR),
+            SerializationKind::Jcs => {
+                canonical::to_vec(&serde_json::from_slice::<serde_json::Value>(&json())?)?
+            }
+            SerializationKind::Mgpk => {
+                let mut data = vec![];
+                serde_transcode::transcode(
+                    &mut serde_json::Deserializer::from_slice(&json()),
+                    &mut rmp_serde::Serializer::new(&mut data).with_struct_map(),
+                )?;
+                data
+            }
+        })
+    }
+
+    /// Sets ledger configuration carried by the block and recomputes its
+    /// fingerprint. Genesis block is governed by its own configuration, so
+    /// it's serialized in kind the configuration selects.
+    pub fn with_config(self, config: LedgerConfig) -> Result<Self> {
+        let version = match self.version.clone() {
+            Some(version) if self.previous.is_none() => Some(Version {
+                kind: config.allowed_serialization_kind(version.kind),
+                ..version
+            }),
+            version => version,
+        };
+        let new_block = Self {
+            version,
+            config: Some(config),
+            ..self
+        };
+        new_block.digest()
+    }
+
+    /// Sets identifier of microledger the block belongs to and recomputes
+    /// its fingerprint.
+    pub fn with_ledger_id(self, ledger_id: SelfAddressingIdentifier) -> Result<Self> {
+        let new_block = Self {
+            ledger_id: Some(ledger_id),
+            ..self
+        };
+        new_block.digest()
+    }
+
+    /// Sets position of the block in microledger and recomputes its
+    /// fingerprint.
+    pub fn with_sequence_number(self, sn: u64) -> Result<Self> {
+        let new_block = Self {
+            sequence_number: Some(sn),
+            ..self
+        };
+        new_block.digest()
+    }
+
+    /// Sets time of block creation and recomputes its fingerprint.
+    pub fn with_timestamp(self, timestamp: DateTime<Utc>) -> Result<Self> {
+        let new_block = Self {
+            timestamp: Some(format_time(timestamp)),
+            ..self
+        };
+        new_block.digest()
+    }
+
+    /// Returns time of block creation, if it was set.
+    pub fn get_timestamp(&self) -> Result<Option<DateTime<Utc>>> {
+        self.timestamp.as_deref().map(parse_time).transpose()
+    }
+
+    /// Sets bounds of time window in which block is valid and recomputes its
+    /// fingerprint. Block can be anchored only within this window.
+    pub fn with_validity(
+        self,
+        not_before: Option<DateTime<Utc>>,
+        not_after: Option<DateTime<Utc>>,
+    ) -> Result<Self> {
+        let new_block = Self {
+            not_before: not_before.map(format_time),
+            not_after: not_after.map(format_time),
+            ..self
+        };
+        new_block.digest()
+    }
+
+    /// Returns start of block validity window, if it was set.
+    pub fn get_not_before(&self) -> Result<Option<DateTime<Utc>>> {
+        self.not_before.as_deref().map(parse_time).transpose()
+    }
+
+    /// Returns end of block validity window, if it was set.
+    pub fn get_not_after(&self) -> Result<Option<DateTime<Utc>>> {
+        self.not_after.as_deref().map(parse_time).transpose()
+    }
+
+    /// Checks if given time is within block validity window.
+    pub fn is_valid_at(&self, time: DateTime<Utc>) -> Result<bool> {
+        let started = self
+            .get_not_before()?
+            .is_none_or(|not_before| not_before <= time);
+        let not_ended = self
+            .get_not_after()?
+            .is_none_or(|not_after| time <= not_after);
+        Ok(started && not_ended)
+    }
+
+    /// Sets header parameter of given name and recomputes block fingerprint.
+    pub fn with_metadata<T: Serialize>(self, key: &str, value: &T) -> Result<Self> {
+        let mut metadata = self.metadata.clone();
+        metadata.insert(key.to_string(), serde_json::to_value(value)?);
+        let new_block = Self { metadata, ..self };
+        new_block.digest()
+    }
+
+    /// Returns header parameter of given name, if it was set.
+    pub fn get_metadata<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        self.metadata
+            .get(key)
+            .map(|value| serde_json::from_value(value.clone()).map_err(Error::EncodeError))
+            .transpose()
     }
 
     pub fn get_fingerprint(&self) -> Result<SelfAddressingIdentifier> {
@@ -65,21 +341,91 @@ impl<I: Identifier + Serialize + Clone> Block<I> {
         SignedBlock {
             block: self,
             signatures,
+            endorsements: vec![],
+            receipts: vec![],
         }
     }
 }
 
+impl<I: Identifier + Serialize + DeserializeOwned + Clone> Block<I> {
+    /// Parses serialized block, checking its version string. Serialization
+    /// kind is recognized from the data itself and has to match the declared
+    /// one. Blocks without version string, created before versioning was
+    /// introduced, are still accepted.
+    pub fn from_slice(data: &[u8]) -> Result<Self> {
+        Self::from_slice_with_limits(data, &Limits::default())
+    }
+
+    /// Parses serialized block like `from_slice`. Size of data is checked
+    /// against limits before it's decoded.
+    pub fn from_slice_with_limits(data: &[u8], limits: &Limits) -> Result<Self> {
+        limits.check_block_size(data.len())?;
+        #[derive(Deserialize)]
+        struct Versioned {
+            #[serde(rename = "v")]
+            version: Option<Version>,
+        }
+        let kind = SerializationKind::sniff(data)?;
+        let Versioned { version } = kind.decode(data)?;
+        if let Some(version) = version {
+            version.check(data.len())?;
+            if !version.kind.is_compatible(kind) {
+                return Err(VersionError::WrongKind {
+                    declared: version.kind.code().to_string(),
+                    actual: kind.code().to_string(),
+                }
+                .into());
+            }
+        }
+        let block: Self = kind.decode(data)?;
+        if block.serialization_kind() == SerializationKind::Jcs && block.encode()? != data {
+            return Err(VersionError::NotCanonical.into());
+        }
+        limits.check_counts(&block)?;
+        Ok(block)
+    }
+}
+
+pub(crate) fn format_time(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+pub(crate) fn parse_time(time: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_e| BlockError::WrongTimestamp(time.to_string()).into())
+}
+
 impl<I: Identifier + Serialize + Clone> Block<I> {
     fn check_previous(&self, previous_block: Option<&Block<I>>) -> Result<bool> {
-        match self.previous {
+        let bound = match self.previous {
             Some(ref prev) => match previous_block {
-                Some(block) => Ok(prev.eq(block
+                Some(block) => prev.eq(block
                     .digital_fingerprint
                     .as_ref()
-                    .ok_or(Error::MissingFingerprintError)?)),
-                None => Err(BlockError::WrongBlockBinding.into()),
+                    .ok_or(Error::MissingFingerprintError)?),
+                None => return Err(BlockError::WrongBlockBinding.into()),
             },
-            None => Ok(previous_block.is_none()),
+            None => previous_block.is_none(),
+        };
+        Ok(bound && self.check_sequence_number(previous_block))
+    }
+
+    /// Checks if sequence number directly follows the previous block one.
+    /// Versioned blocks have to carry it, older blocks without it aren't
+    /// checked.
+    fn check_sequence_number(&self, previous_block: Option<&Block<I>>) -> bool {
+        match (self.sequence_number, previous_block) {
+            (None, _) => self.version.is_none(),
+            (Some(sn), None) => sn == 0,
+            (
+                Some(sn),
+                Some(Block {
+                    sequence_number: Some(previous_sn),
+                    ..
+                }),
+            ) => previous_sn.checked_add(1) == Some(sn),
+            _ => true,
         }
     }
 }
@@ -92,6 +438,14 @@ where
 {
     pub block: Block<I>,
     pub signatures: Vec<S>,
+    /// Signatures of identifiers which aren't controlling the block, ie.
+    /// auditors. They can be added after the block is anchored, as they
+    /// don't change its fingerprint.
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    pub endorsements: Vec<S>,
+    /// Acknowledgements of witnesses which have seen the block.
+    #[serde(default = "Vec::new", skip_serializing_if = "Vec::is_empty")]
+    pub receipts: Vec<Receipt<I, S>>,
 }
 
 // Checks if signed block matches the given block.
@@ -104,17 +458,127 @@ where
         Self {
             block,
             signatures: sigs,
+            endorsements: vec![],
+            receipts: vec![],
         }
     }
-    pub fn verify<V: Verifier<Signature = S>>(&self, verifier: Arc<V>) -> Result<bool> {
+
+    /// Adds endorsements to the block.
+    pub fn with_endorsements(mut self, endorsements: Vec<S>) -> Self {
+        self.endorsements.extend(endorsements);
+        self
+    }
+
+    /// Verifies block signatures. Returns their statuses in the order of
+    /// signatures.
+    pub fn verify_signatures<V: Verifier<Signature = S>>(
+        &self,
+        verifier: Arc<V>,
+    ) -> Result<Vec<SignatureStatus>> {
         verifier.verify(&Encode::encode(&self.block)?, self.signatures.clone())
     }
 
-    pub fn check_controlling_identifiers(&self, controlling_identifiers: &[I]) -> bool {
+    /// Checks if all block signatures are valid.
+    pub fn verify<V: Verifier<Signature = S>>(&self, verifier: Arc<V>) -> Result<bool> {
+        let statuses = self.verify_signatures(verifier)?;
+        Ok(first_failure(&statuses, self.signatures.len()).is_none())
+    }
+
+    /// Adds witness receipts to the block.
+    pub fn with_receipts(mut self, receipts: Vec<Receipt<I, S>>) -> Self {
+        self.receipts.extend(receipts);
+        self
+    }
+
+    /// Verifies endorsements with the same verifier as block signatures.
+    /// Block without endorsements is valid.
+    pub fn verify_endorsements<V: Verifier<Signature = S>>(
+        &self,
+        verifier: Arc<V>,
+    ) -> Result<bool> {
+        if self.endorsements.is_empty() {
+            return Ok(true);
+        }
+        let statuses = verifier.verify(&Encode::encode(&self.block)?, self.endorsements.clone())?;
+        Ok(first_failure(&statuses, self.endorsements.len()).is_none())
+    }
+
+    /// Returns identifiers which endorsed the block.
+    pub fn endorsers(&self) -> Vec<I> {
+        self.endorsements
+            .iter()
+            .flat_map(|endorsement| endorsement.get_signers())
+            .collect()
+    }
+
+    /// Checks if every endorsement identifies its signers, and if none of
+    /// them is among given controlling identifiers or endorsed the block
+    /// twice.
+    pub fn check_endorsers(&self, controlling_identifiers: &[I]) -> Result<()> {
+        if self
+            .endorsements
+            .iter()
+            .any(|endorsement| endorsement.get_signers().is_empty())
+        {
+            return Err(BlockError::UnattributableSignatures.into());
+        }
+        let endorsers = self.endorsers();
+        if endorsers
+            .iter()
+            .any(|endorser| controlling_identifiers.contains(endorser))
+        {
+            return Err(BlockError::EndorsedByController.into());
+        }
+        if endorsers
+            .iter()
+            .enumerate()
+            .any(|(i, endorser)| endorsers[..i].contains(endorser))
+        {
+            return Err(BlockError::DuplicatedSigner.into());
+        }
+        Ok(())
+    }
+
+    /// Checks if all signers are among given controlling identifiers. Block
+    /// needs at least one signature identifying its signer, and no identifier
+    /// can sign it twice.
+    pub fn check_controlling_identifiers(&self, controlling_identifiers: &[I]) -> Result<bool> {
+        if self.signatures.is_empty() {
+            return Err(BlockError::MissingSignatures.into());
+        }
+        let signers: Vec<I> = self
+            .signatures
+            .iter()
+            .flat_map(|signature| signature.get_signers())
+            .collect();
+        if signers.is_empty() {
+            return Err(BlockError::UnattributableSignatures.into());
+        }
+        if signers
+            .iter()
+            .enumerate()
+            .any(|(i, signer)| signers[..i].contains(signer))
+        {
+            return Err(BlockError::DuplicatedSigner.into());
+        }
+        Ok(signers
+            .iter()
+            .all(|signer| controlling_identifiers.contains(signer)))
+    }
+
+    /// Returns number of distinct controlling identifiers that signed the block.
+    pub fn count_signers(&self, controlling_identifiers: &[I]) -> usize {
+        let mut signers: Vec<I> = vec![];
         self.signatures
             .iter()
-            .filter_map(|signature| signature.get_signer())
-            .all(|signer| controlling_identifiers.contains(&signer))
+            .flat_map(|signature| signature.get_signers())
+            .filter(|signer| controlling_identifiers.contains(signer))
+            .for_each(|signer| {
+                if !signers.contains(&signer) {
+                    signers.push(signer)
+                }
+            });
+        signers.len()
     }
 
     pub fn check_previous_block(&self, block: Option<&Block<I>>) -> Result<bool> {
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use said::derivation::{HashFunction, HashFunctionCode};
+use said::SelfAddressingIdentifier;
+use serde::Serialize;
+
+use crate::{
+    block::{format_time, Block, BlockError},
+    config::LedgerConfig,
+    microledger::MicroLedger,
+    seal_bundle::SealBundle,
+    seals::Seal,
+    verifier::Verifier,
+    version::{SerializationKind, Version},
+    Identifier, Result, Signature,
+};
+
+/// Builds block step by step. Block is validated against ledger
+/// configuration before its fingerprint is computed, so improper block is
+/// rejected before anybody signs it.
+#[derive(Clone, Debug)]
+pub struct BlockBuilder<I: Identifier + Serialize + Clone> {
+    seals: Vec<Seal>,
+    controlling_identifiers: Vec<I>,
+    previous: Option<SelfAddressingIdentifier>,
+    ledger_id: Option<SelfAddressingIdentifier>,
+    sequence_number: Option<u64>,
+    timestamp: Option<DateTime<Utc>>,
+    not_before: Option<DateTime<Utc>>,
+    not_after: Option<DateTime<Utc>>,
+    metadata: BTreeMap<String, serde_json::Value>,
+    config: Option<LedgerConfig>,
+    threshold: Option<usize>,
+    ledger_config: Option<LedgerConfig>,
+    serialization_kind: SerializationKind,
+    hash_algorithm: HashFunctionCode,
+    terminal: bool,
+}
+
+impl<I: Identifier + Serialize + Clone> Default for BlockBuilder<I> {
+    fn default() -> Self {
+        Self {
+            seals: vec![],
+            controlling_identifiers: vec![],
+            previous: None,
+            ledger_id: None,
+            sequence_number: None,
+            timestamp: None,
+            not_before: None,
+            not_after: None,
+            metadata: BTreeMap::new(),
+            config: None,
+            threshold: None,
+            ledger_config: None,
+            serialization_kind: SerializationKind::Json,
+            hash_algorithm: HashFunctionCode::Blake3_256,
+            terminal: false,
+        }
+    }
+}
+
+impl<I: Identifier + Serialize + Clone> BlockBuilder<I> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds seals to the block.
+    pub fn with_seals(mut self, seals: Vec<Seal>) -> Self {
+        self.seals.extend(seals);
+        self
+    }
+
+    /// Adds fingerprints of all data in seal bundle to the block.
+    pub fn with_seal_bundle(self, seal_bundle: &SealBundle) -> Self {
+        self.with_seals(seal_bundle.get_fingerprints())
+    }
+
+    /// Sets identifiers which will control the ledger after this block.
+    pub fn with_controlling_identifiers(self, controlling_identifiers: Vec<I>) -> Self {
+        Self {
+            controlling_identifiers,
+            ..self
+        }
+    }
+
+    /// Sets previous block explicitly. Use `with_previous_from` to bind block
+    /// to the last block of a ledger.
+    pub fn with_previous(self, previous: SelfAddressingIdentifier) -> Self {
+        Self {
+            previous: Some(previous),
+            ..self
+        }
+    }
+
+    /// Binds block to the last block of given ledger. Sequence number, ledger
+    /// identifier, timestamp and serialization kind are derived from the
+    /// ledger, and the block is validated against its current configuration.
+    pub fn with_previous_from<S, V>(self, ledger: &MicroLedger<S, V, I>) -> Result<Self>
+    where
+        S: Serialize + Clone + Signature<Identifier = I>,
+        V: Verifier<Signature = S>,
+        I: PartialEq,
+    {
+        let previous = ledger.last_fingerprint()?;
+        let ledger_config = ledger.get_last_block().map(|_| ledger.current_config());
+        Ok(Self {
+            previous,
+            ledger_id: ledger.id().cloned(),
+            sequence_number: Some(ledger.blocks.len() as u64),
+            timestamp: Some(ledger.now()),
+            serialization_kind: ledger.next_serialization_kind(),
+            ledger_config,
+            ..self
+        })
+    }
+
+    /// Sets time of block creation.
+    pub fn with_timestamp(self, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            timestamp: Some(timestamp),
+            ..self
+        }
+    }
+
+    /// Sets bounds of time window in which block is valid.
+    pub fn with_validity(
+        self,
+        not_before: Option<DateTime<Utc>>,
+        not_after: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            not_before,
+            not_after,
+            ..self
+        }
+    }
+
+    /// Sets ledger configuration carried by the block. In other than genesis
+    /// block it amends configuration of the ledger.
+    pub fn with_config(self, config: LedgerConfig) -> Self {
+        Self {
+            config: Some(config),
+            ..self
+        }
+    }
+
+    /// Sets number of controlling identifiers which need to sign the next
+    /// block. Other parameters are taken from configuration set by
+    /// `with_config`, or from the current ledger configuration when the block
+    /// is built, so in other than genesis block it amends it.
+    pub fn with_threshold(self, threshold: usize) -> Self {
+        Self {
+            threshold: Some(threshold),
+            ..self
+        }
+    }
+
+    /// Sets header parameter of given name.
+    pub fn with_metadata<T: Serialize>(mut self, key: &str, value: &T) -> Result<Self> {
+        self.metadata
+            .insert(key.to_string(), serde_json::to_value(value)?);
+        Ok(self)
+    }
+
+    /// Sets serialization kind of the block.
+    pub fn with_serialization_kind(self, serialization_kind: SerializationKind) -> Self {
+        Self {
+            serialization_kind,
+            ..self
+        }
+    }
+
+    /// Sets digest algorithm of block fingerprint.
+    pub fn with_hash_algorithm(self, hash_algorithm: HashFunctionCode) -> Self {
+        Self {
+            hash_algorithm,
+            ..self
+        }
+    }
+
+    /// Makes the block terminate the ledger. Terminal block has no
+    /// controlling identifiers.
+    pub fn terminal(self) -> Self {
+        Self {
+            terminal: true,
+            controlling_identifiers: vec![],
+            ..self
+        }
+    }
+
+    /// Validates the block and computes its fingerprint.
+    pub fn build(self) -> Result<Block<I>> {
+        if let (Some(not_before), Some(not_after)) = (self.not_before, self.not_after) {
+            if not_before > not_after {
+                return Err(BlockError::WrongValidityWindow.into());
+            }
+        }
+        // Threshold amends configuration set explicitly, or the governing
+        // one.
+        let ledger_config = &self.ledger_config;
+        let config = match self.threshold {
+            Some(threshold) => Some(LedgerConfig {
+                threshold,
+                ..self
+                    .config
+                    .or_else(|| ledger_config.clone())
+                    .unwrap_or_default()
+            }),
+            None => self.config,
+        };
+        // Genesis block is serialized in kind selected by its own
+        // configuration.
+        let serialization_kind = match (&self.ledger_config, &config) {
+            (None, Some(config)) => config.allowed_serialization_kind(self.serialization_kind),
+            _ => self.serialization_kind,
+        };
+        let block = Block {
+            version: Some(Version::new(serialization_kind, 0)),
+            controlling_identifiers: self.controlling_identifiers,
+            digital_fingerprint: None,
+            ledger_id: self.ledger_id,
+            sequence_number: self.sequence_number,
+            timestamp: self.timestamp.map(format_time),
+            not_before: self.not_before.map(format_time),
+            not_after: self.not_after.map(format_time),
+            previous: self.previous,
+            seals: self.seals,
+            metadata: self.metadata,
+            config,
+            terminal: self.terminal,
+        };
+
+        // Genesis block is governed by configuration it carries.
+        let config = self
+            .ledger_config
+            .or_else(|| block.config.clone())
+            .unwrap_or_default();
+        config.check_block(&block)?;
+        config
+            .check_hash_algorithm(&HashFunction::from(self.hash_algorithm.clone()).derive(&[]))?;
+        if !block.terminal {
+            block
+                .config
+                .as_ref()
+                .unwrap_or(&config)
+                .check_threshold(block.controlling_identifiers.len())?;
+        }
+
+        block.with_hash_algorithm(self.hash_algorithm)
+    }
+}
@@ -0,0 +1,193 @@
+//! JSON Canonicalization Scheme (JCS) as defined in [RFC 8785]. Canonical
+//! serialization doesn't depend on field order of serialized structures, so
+//! it can be reproduced by any implementation.
+//!
+//! [RFC 8785]: https://www.rfc-editor.org/rfc/rfc8785
+
+use serde::Serialize;
+use serde_json::{Number, Value};
+
+use crate::Result;
+
+/// Serializes value into canonical JSON.
+pub fn to_vec<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    let value = serde_json::to_value(value)?;
+    let mut out = String::new();
+    write_value(&value, &mut out)?;
+    Ok(out.into_bytes())
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<()> {
+    match value {
+        Value::Null | Value::Bool(_) | Value::String(_) => {
+            out.push_str(&serde_json::to_string(value)?)
+        }
+        Value::Number(number) => out.push_str(&format_number(number)),
+        Value::Array(values) => {
+            out.push('[');
+            for (i, value) in values.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(value, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            // Properties are sorted by UTF-16 code units of their names.
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::to_string(key)?);
+                out.push(':');
+                write_value(value, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+/// Formats number the way ECMAScript `Number.prototype.toString` does.
+/// Every number is treated as IEEE 754 double, so integers above 2^53
+/// lose precision.
+fn format_number(number: &Number) -> String {
+    let value = match (number.as_u64(), number.as_i64(), number.as_f64()) {
+        (Some(n), _, _) => n as f64,
+        (_, Some(n), _) => n as f64,
+        (_, _, Some(n)) => n,
+        _ => 0.,
+    };
+    if value == 0. {
+        return "0".to_string();
+    }
+    // Shortest representation which round-trips, ie. `1.2345e3`.
+    let (digits, exponent) = split_scientific(&format!("{:e}", value.abs()));
+    let (digits, exponent) = round_half_even(digits, exponent, value.abs());
+    let k = digits.len() as i32;
+    let n = exponent + 1;
+
+    let formatted = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat(-n as usize), digits)
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        if rest.is_empty() {
+            format!("{}e{}{}", first, sign, (n - 1).abs())
+        } else {
+            format!("{}.{}e{}{}", first, rest, sign, (n - 1).abs())
+        }
+    };
+    if value < 0. {
+        format!("-{}", formatted)
+    } else {
+        formatted
+    }
+}
+
+/// Splits scientific notation into significant digits and exponent.
+fn split_scientific(scientific: &str) -> (String, i32) {
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap_or(0));
+    let digits = mantissa.replace('.', "");
+    let exponent = exponent[1..].parse().unwrap_or_default();
+    (digits, exponent)
+}
+
+/// When number lies exactly halfway between two shortest representations,
+/// ECMAScript picks the even one, while Rust formatting may pick the odd one.
+fn round_half_even(digits: String, exponent: i32, value: f64) -> (String, i32) {
+    // Exact decimal expansion, any double fits in 800 digits.
+    let (exact, exact_exponent) = split_scientific(&format!("{:.800e}", value));
+    let exact = exact.trim_end_matches('0');
+    if exact_exponent != exponent || exact.len() != digits.len() + 1 || !exact.ends_with('5') {
+        return (digits, exponent);
+    }
+    let lower: u64 = match exact[..digits.len()].parse() {
+        Ok(lower) => lower,
+        Err(_) => return (digits, exponent),
+    };
+    let even = if lower.is_multiple_of(2) {
+        lower
+    } else {
+        lower + 1
+    };
+    let even = even.to_string();
+    // Rounding up may carry to the next power of ten, ie. 999 -> 1000.
+    let exponent = exponent + (even.len() - digits.len()) as i32;
+    (even.trim_end_matches('0').to_string(), exponent)
+}
+
+#[test]
+pub fn test_number_serialization() {
+    // Test vectors from RFC 8785, Appendix B.
+    let vectors = [
+        (0x0000000000000000, "0"),
+        (0x8000000000000000, "0"),
+        (0x0000000000000001, "5e-324"),
+        (0x8000000000000001, "-5e-324"),
+        (0x7fefffffffffffff, "1.7976931348623157e+308"),
+        (0xffefffffffffffff, "-1.7976931348623157e+308"),
+        (0x4340000000000000, "9007199254740992"),
+        (0xc340000000000000, "-9007199254740992"),
+        (0x4430000000000000, "295147905179352830000"),
+        (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+        (0x44b52d02c7e14af6, "1e+23"),
+        (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+        (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+        (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+        (0x444b1ae4d6e2ef50, "1e+21"),
+        (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+        (0x3eb0c6f7a0b5ed8d, "0.000001"),
+        (0x41b3de4355555553, "333333333.3333332"),
+        (0x41b3de4355555554, "333333333.33333325"),
+        (0x41b3de4355555555, "333333333.3333333"),
+        (0x41b3de4355555556, "333333333.3333334"),
+        (0x41b3de4355555557, "333333333.33333343"),
+        (0xbecbf647612f3696, "-0.0000033333333333333333"),
+        (0x43143ff3c1cb0959, "1424953923781206.2"),
+    ];
+    for (bits, expected) in vectors {
+        let number = Number::from_f64(f64::from_bits(bits)).unwrap();
+        assert_eq!(format_number(&number), expected);
+    }
+}
+
+#[test]
+pub fn test_canonicalization() {
+    // Test vectors from RFC 8785, sections 3.2.2 and 3.2.3.
+    let input = r#"{
+        "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+        "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+        "literals": [null, true, false]
+    }"#;
+    let value: Value = serde_json::from_str(input).unwrap();
+    assert_eq!(
+        String::from_utf8(to_vec(&value).unwrap()).unwrap(),
+        r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+    );
+
+    let input = r#"{
+        "\u20ac": "Euro Sign",
+        "\r": "Carriage Return",
+        "\ufb33": "Hebrew Letter Dalet With Dagesh",
+        "1": "One",
+        "\ud83d\ude00": "Emoji: Grinning Face",
+        "\u0080": "Control",
+        "\u00f6": "Latin Small Letter O With Diaeresis"
+    }"#;
+    let value: Value = serde_json::from_str(input).unwrap();
+    assert_eq!(
+        String::from_utf8(to_vec(&value).unwrap()).unwrap(),
+        "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+         \"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\"\u{20ac}\":\"Euro Sign\",\
+         \"\u{1f600}\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}"
+    );
+}
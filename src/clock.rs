@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+
+/// Source of current time, used to validate block timestamps.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Clock based on system time.
+#[derive(Default, Debug, Clone)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
@@ -0,0 +1,294 @@
+//! Identifiers and signatures of different backends, so controllers of one
+//! ledger don't have to use the same kind of identifier.
+
+#[cfg(any(feature = "did", feature = "keriox"))]
+use std::sync::Arc;
+
+#[cfg(feature = "keriox")]
+use keri::prefix::IdentifierPrefix;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "did")]
+use crate::did::{Did, DidSignature};
+#[cfg(any(feature = "secp256k1", feature = "p256"))]
+use crate::ecdsa;
+#[cfg(feature = "ed25519")]
+use crate::ed25519::{Ed25519Identifier, Ed25519Signature, Ed25519Verifier};
+#[cfg(feature = "keriox")]
+use crate::keri::{resolve_indexed, verifier::KeriVerifier, KeriSignature};
+use crate::{
+    verifier::{SignatureStatus, Verifier},
+    Identifier, Result, Signature,
+};
+
+/// Controlling identifier of any supported kind. It's serialized together
+/// with its kind, as the same string can be valid identifier of several
+/// kinds, eg. Ed25519 public key and KERI basic prefix.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CompositeIdentifier {
+    #[cfg(feature = "ed25519")]
+    #[serde(rename = "ed25519")]
+    Ed25519(Ed25519Identifier),
+    #[cfg(feature = "secp256k1")]
+    #[serde(rename = "secp256k1")]
+    Secp256k1(ecdsa::secp256k1::Secp256k1Identifier),
+    #[cfg(feature = "p256")]
+    #[serde(rename = "p256")]
+    P256(ecdsa::p256::P256Identifier),
+    #[cfg(feature = "did")]
+    #[serde(rename = "did")]
+    Did(Did),
+    #[cfg(feature = "keriox")]
+    #[serde(rename = "keri")]
+    Keri(IdentifierPrefix),
+}
+
+impl Identifier for CompositeIdentifier {}
+
+/// Signature of any supported kind. Its signers are identifiers of the
+/// same kind.
+// Ed25519 signatures keep expanded public keys of their signers.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CompositeSignature {
+    #[cfg(feature = "ed25519")]
+    #[serde(rename = "ed25519")]
+    Ed25519(Ed25519Signature),
+    #[cfg(feature = "secp256k1")]
+    #[serde(rename = "secp256k1")]
+    Secp256k1(ecdsa::secp256k1::Secp256k1Signature),
+    #[cfg(feature = "p256")]
+    #[serde(rename = "p256")]
+    P256(ecdsa::p256::P256Signature),
+    #[cfg(feature = "did")]
+    #[serde(rename = "did")]
+    Did(DidSignature),
+    #[cfg(feature = "keriox")]
+    #[serde(rename = "keri")]
+    Keri(KeriSignature),
+}
+
+macro_rules! impl_from {
+    ($feature:literal, $variant:ident, $identifier:ty, $signature:ty) => {
+        #[cfg(feature = $feature)]
+        impl From<$identifier> for CompositeIdentifier {
+            fn from(identifier: $identifier) -> Self {
+                CompositeIdentifier::$variant(identifier)
+            }
+        }
+
+        #[cfg(feature = $feature)]
+        impl From<$signature> for CompositeSignature {
+            fn from(signature: $signature) -> Self {
+                CompositeSignature::$variant(signature)
+            }
+        }
+    };
+}
+
+impl_from!("ed25519", Ed25519, Ed25519Identifier, Ed25519Signature);
+impl_from!(
+    "secp256k1",
+    Secp256k1,
+    ecdsa::secp256k1::Secp256k1Identifier,
+    ecdsa::secp256k1::Secp256k1Signature
+);
+impl_from!(
+    "p256",
+    P256,
+    ecdsa::p256::P256Identifier,
+    ecdsa::p256::P256Signature
+);
+impl_from!("did", Did, Did, DidSignature);
+impl_from!("keriox", Keri, IdentifierPrefix, KeriSignature);
+
+fn signers_of<S>(signature: &S) -> Vec<CompositeIdentifier>
+where
+    S: Signature,
+    S::Identifier: Into<CompositeIdentifier>,
+{
+    signature
+        .get_signers()
+        .into_iter()
+        .map(Into::into)
+        .collect()
+}
+
+impl Signature for CompositeSignature {
+    type Identifier = CompositeIdentifier;
+
+    fn get_signers(&self) -> Vec<Self::Identifier> {
+        match self {
+            #[cfg(feature = "ed25519")]
+            CompositeSignature::Ed25519(signature) => signers_of(signature),
+            #[cfg(feature = "secp256k1")]
+            CompositeSignature::Secp256k1(signature) => signers_of(signature),
+            #[cfg(feature = "p256")]
+            CompositeSignature::P256(signature) => signers_of(signature),
+            #[cfg(feature = "did")]
+            CompositeSignature::Did(signature) => signers_of(signature),
+            #[cfg(feature = "keriox")]
+            CompositeSignature::Keri(signature) => signers_of(signature),
+        }
+    }
+
+    /// Indexes of KERI indexed signatures point to positions in the whole
+    /// list of controlling identifiers and have to refer to KERI basic
+    /// prefixes.
+    fn resolve(&self, _controlling_identifiers: &[Self::Identifier]) -> Result<Option<Self>> {
+        match self {
+            #[cfg(feature = "keriox")]
+            CompositeSignature::Keri(signature) => Ok(resolve_indexed(signature, |index| {
+                match _controlling_identifiers.get(index) {
+                    Some(CompositeIdentifier::Keri(prefix)) => Some(prefix),
+                    _ => None,
+                }
+            })?
+            .map(CompositeSignature::Keri)),
+            #[allow(unreachable_patterns)]
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Verifies signatures of every kind with verifier of their backend.
+/// Verifiers of DID and KERI signatures need to be provided, as they depend
+/// on resolvers and key event logs. Signatures without verifier are
+/// reported as unsupported.
+#[derive(Clone, Default)]
+pub struct CompositeVerifier {
+    #[cfg(feature = "did")]
+    did: Option<Arc<dyn Verifier<Signature = DidSignature> + Send + Sync>>,
+    #[cfg(feature = "keriox")]
+    keri: Option<Arc<KeriVerifier>>,
+}
+
+impl CompositeVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[cfg(feature = "did")]
+    pub fn with_did<V>(mut self, verifier: Arc<V>) -> Self
+    where
+        V: Verifier<Signature = DidSignature> + Send + Sync + 'static,
+    {
+        self.did = Some(verifier);
+        self
+    }
+
+    #[cfg(feature = "keriox")]
+    pub fn with_keri(mut self, verifier: Arc<KeriVerifier>) -> Self {
+        self.keri = Some(verifier);
+        self
+    }
+}
+
+/// Verifies signatures selected for given backend verifier in one batch
+/// and puts their statuses at positions of the signatures.
+fn dispatch<V: Verifier + ?Sized>(
+    verifier: &V,
+    data: &[u8],
+    signatures: &[CompositeSignature],
+    statuses: &mut [SignatureStatus],
+    select: impl Fn(&CompositeSignature) -> Option<V::Signature>,
+) -> Result<()> {
+    let (positions, selected): (Vec<_>, Vec<_>) = signatures
+        .iter()
+        .enumerate()
+        .filter_map(|(i, signature)| select(signature).map(|signature| (i, signature)))
+        .unzip();
+    if selected.is_empty() {
+        return Ok(());
+    }
+    let verified = verifier.verify(data, selected)?;
+    for (n, i) in positions.into_iter().enumerate() {
+        statuses[i] = verified.get(n).copied().unwrap_or(SignatureStatus::Invalid);
+    }
+    Ok(())
+}
+
+impl Verifier for CompositeVerifier {
+    type Signature = CompositeSignature;
+
+    fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>> {
+        let mut statuses = vec![SignatureStatus::UnsupportedType; s.len()];
+        #[cfg(feature = "ed25519")]
+        dispatch(&Ed25519Verifier, data, &s, &mut statuses, |sig| match sig {
+            CompositeSignature::Ed25519(sig) => Some(sig.clone()),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        })?;
+        #[cfg(feature = "secp256k1")]
+        dispatch(
+            &ecdsa::secp256k1::Secp256k1Verifier,
+            data,
+            &s,
+            &mut statuses,
+            |sig| match sig {
+                CompositeSignature::Secp256k1(sig) => Some(sig.clone()),
+                #[allow(unreachable_patterns)]
+                _ => None,
+            },
+        )?;
+        #[cfg(feature = "p256")]
+        dispatch(
+            &ecdsa::p256::P256Verifier,
+            data,
+            &s,
+            &mut statuses,
+            |sig| match sig {
+                CompositeSignature::P256(sig) => Some(sig.clone()),
+                #[allow(unreachable_patterns)]
+                _ => None,
+            },
+        )?;
+        #[cfg(feature = "did")]
+        if let Some(verifier) = &self.did {
+            dispatch(
+                verifier.as_ref(),
+                data,
+                &s,
+                &mut statuses,
+                |sig| match sig {
+                    CompositeSignature::Did(sig) => Some(sig.clone()),
+                    _ => None,
+                },
+            )?;
+        }
+        #[cfg(feature = "keriox")]
+        if let Some(verifier) = &self.keri {
+            dispatch(
+                verifier.as_ref(),
+                data,
+                &s,
+                &mut statuses,
+                |sig| match sig {
+                    CompositeSignature::Keri(sig) => Some(sig.clone()),
+                    #[allow(unreachable_patterns)]
+                    _ => None,
+                },
+            )?;
+        }
+        Ok(statuses)
+    }
+
+    /// Passes accepted DID signatures to DID verifier, so it can record keys
+    /// which verified them.
+    fn accept(&self, _data: &[u8], _s: Vec<Self::Signature>) -> Result<()> {
+        #[cfg(feature = "did")]
+        if let Some(verifier) = &self.did {
+            let selected: Vec<_> = _s
+                .into_iter()
+                .filter_map(|sig| match sig {
+                    CompositeSignature::Did(sig) => Some(sig),
+                    _ => None,
+                })
+                .collect();
+            if !selected.is_empty() {
+                verifier.accept(_data, selected)?;
+            }
+        }
+        Ok(())
+    }
+}
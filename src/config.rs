@@ -0,0 +1,135 @@
+use said::{derivation::HashFunctionCode, SelfAddressingIdentifier};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{block::Block, seals::Seal, version::SerializationKind, Identifier, Result};
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Digest algorithm of {0} is not allowed in this ledger")]
+    DisallowedHashAlgorithm(String),
+    #[error("Seal type {0} is not allowed in this ledger")]
+    DisallowedSealType(String),
+    #[error("Serialization kind {0} is not allowed in this ledger")]
+    DisallowedSerializationKind(String),
+    #[error("Threshold must be between 1 and number of controlling identifiers")]
+    ImproperThreshold,
+    #[error("Not enough controlling identifiers signed the block")]
+    ThresholdNotMet,
+}
+
+/// Ledger parameters set in the genesis block. They apply to every subsequent
+/// block, until some block amends them by carrying a new configuration.
+/// Amendment has to be signed by all current controlling identifiers.
+#[derive(Clone, Serialize, Deserialize, PartialEq, Debug)]
+pub struct LedgerConfig {
+    /// Human readable name of the ledger.
+    #[serde(rename = "n", default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// CESR codes of digest algorithms allowed for block and seal
+    /// fingerprints, ie. `E` for Blake3-256. Empty list allows any algorithm.
+    #[serde(rename = "ha", default, skip_serializing_if = "Vec::is_empty")]
+    pub hash_algorithms: Vec<String>,
+    /// Allowed seal type codes, ie. `A` for attached seal. Empty list allows
+    /// any seal type.
+    #[serde(rename = "st", default, skip_serializing_if = "Vec::is_empty")]
+    pub seal_types: Vec<String>,
+    /// Allowed serialization kind codes, ie. `JCSN` for canonical JSON. New
+    /// blocks are serialized in the first one. Empty list allows any kind.
+    #[serde(rename = "sk", default, skip_serializing_if = "Vec::is_empty")]
+    pub serialization_kinds: Vec<String>,
+    /// Minimal number of controlling identifiers which need to sign the next
+    /// block.
+    #[serde(rename = "kt")]
+    pub threshold: usize,
+}
+
+impl Default for LedgerConfig {
+    fn default() -> Self {
+        Self {
+            name: None,
+            hash_algorithms: vec![],
+            seal_types: vec![],
+            serialization_kinds: vec![],
+            threshold: 1,
+        }
+    }
+}
+
+impl LedgerConfig {
+    /// Checks if block fingerprints and seals are allowed by configuration.
+    pub fn check_block<I: Identifier + Serialize + Clone>(&self, block: &Block<I>) -> Result<()> {
+        if !self.hash_algorithms.is_empty() {
+            let digests = block
+                .digital_fingerprint
+                .iter()
+                .chain(block.seals.iter().map(Seal::digest));
+            for digest in digests {
+                self.check_hash_algorithm(digest)?;
+            }
+        }
+        if !self.serialization_kinds.is_empty() {
+            let kind = block.serialization_kind().code();
+            if !self.serialization_kinds.iter().any(|sk| sk == kind) {
+                return Err(ConfigError::DisallowedSerializationKind(kind.into()).into());
+            }
+        }
+        if !self.seal_types.is_empty() {
+            if let Some(seal) = block
+                .seals
+                .iter()
+                .find(|seal| !self.seal_types.iter().any(|st| st == seal.seal_type()))
+            {
+                return Err(ConfigError::DisallowedSealType(seal.seal_type().into()).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if digest algorithm of given digest is allowed. Derivation
+    /// code of the digest has to match one of configured codes exactly,
+    /// entries which aren't digest codes match nothing.
+    pub fn check_hash_algorithm(&self, digest: &SelfAddressingIdentifier) -> Result<()> {
+        let algorithm = HashFunctionCode::from(&digest.derivation);
+        if self.hash_algorithms.is_empty()
+            || self.hash_algorithms.iter().any(
+                |code| matches!(code.parse::<HashFunctionCode>(), Ok(code) if code == algorithm),
+            )
+        {
+            Ok(())
+        } else {
+            Err(ConfigError::DisallowedHashAlgorithm(digest.to_string()).into())
+        }
+    }
+
+    /// Serialization kind of new blocks, if configuration selects one.
+    pub fn serialization_kind(&self) -> Option<SerializationKind> {
+        self.serialization_kinds
+            .iter()
+            .find_map(|code| code.parse().ok())
+    }
+
+    /// Returns given serialization kind if configuration allows it, or the
+    /// one selected by configuration otherwise.
+    pub fn allowed_serialization_kind(&self, preferred: SerializationKind) -> SerializationKind {
+        if self
+            .serialization_kinds
+            .iter()
+            .any(|sk| sk == preferred.code())
+        {
+            preferred
+        } else {
+            self.serialization_kind().unwrap_or(preferred)
+        }
+    }
+
+    /// Checks if threshold can be satisfied by given number of controlling
+    /// identifiers.
+    pub fn check_threshold(&self, controllers_count: usize) -> Result<()> {
+        if self.threshold == 0 || self.threshold > controllers_count {
+            Err(ConfigError::ImproperThreshold.into())
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,358 @@
+use std::{fmt, str::FromStr};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::Verifier as _;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::DidError;
+use crate::{
+    ed25519::{Ed25519Identifier, Ed25519Signature},
+    verifier::{SignatureStatus, Verifier},
+    Identifier, Result, Signature,
+};
+
+const DID_KEY_PREFIX: &str = "did:key:";
+/// Multibase prefix of base58btc encoding.
+const BASE58_BTC: char = 'z';
+
+/// Multicodec prefixes of public keys, as unsigned varints.
+const ED25519_CODEC: [u8; 2] = [0xed, 0x01];
+#[cfg(feature = "secp256k1")]
+const SECP256K1_CODEC: [u8; 2] = [0xe7, 0x01];
+#[cfg(feature = "p256")]
+const P256_CODEC: [u8; 2] = [0x80, 0x24];
+
+/// Public key encoded in `did:key` identifier. Keys of secp256k1 and P-256
+/// curves are available only with `secp256k1` and `p256` features, otherwise
+/// they are rejected as unsupported.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DidPublicKey {
+    Ed25519(ed25519_dalek::PublicKey),
+    #[cfg(feature = "secp256k1")]
+    Secp256k1(k256::ecdsa::VerifyingKey),
+    #[cfg(feature = "p256")]
+    P256(p256::ecdsa::VerifyingKey),
+}
+
+impl DidPublicKey {
+    /// Returns key prefixed with its multicodec code. ECDSA keys are
+    /// compressed.
+    fn to_multicodec(&self) -> Vec<u8> {
+        let (codec, key) = match self {
+            DidPublicKey::Ed25519(key) => (ED25519_CODEC, key.as_bytes().to_vec()),
+            #[cfg(feature = "secp256k1")]
+            DidPublicKey::Secp256k1(key) => (
+                SECP256K1_CODEC,
+                key.to_encoded_point(true).as_bytes().to_vec(),
+            ),
+            #[cfg(feature = "p256")]
+            DidPublicKey::P256(key) => (P256_CODEC, key.to_encoded_point(true).as_bytes().to_vec()),
+        };
+        [codec.to_vec(), key].concat()
+    }
+
+    fn from_multicodec(data: &[u8], multibase: &str) -> Result<Self> {
+        let unsupported = || DidError::UnsupportedKeyType(multibase.to_string());
+        if data.len() < 2 {
+            return Err(unsupported().into());
+        }
+        let (codec, key) = data.split_at(2);
+        let public_key = match codec {
+            c if c == ED25519_CODEC => ed25519_dalek::PublicKey::from_bytes(key)
+                .map(DidPublicKey::Ed25519)
+                .map_err(|_e| DidError::WrongPublicKey)?,
+            #[cfg(feature = "secp256k1")]
+            c if c == SECP256K1_CODEC => k256::ecdsa::VerifyingKey::from_sec1_bytes(key)
+                .map(DidPublicKey::Secp256k1)
+                .map_err(|_e| DidError::WrongPublicKey)?,
+            #[cfg(feature = "p256")]
+            c if c == P256_CODEC => p256::ecdsa::VerifyingKey::from_sec1_bytes(key)
+                .map(DidPublicKey::P256)
+                .map_err(|_e| DidError::WrongPublicKey)?,
+            _ => return Err(unsupported().into()),
+        };
+        Ok(public_key)
+    }
+
+    /// Encodes key as base58btc multibase string, as in `did:key`
+    /// identifiers and `publicKeyMultibase` of verification methods.
+    pub fn to_multibase(&self) -> String {
+        format!(
+            "{}{}",
+            BASE58_BTC,
+            bs58::encode(self.to_multicodec()).into_string()
+        )
+    }
+
+    pub fn from_multibase(multibase: &str) -> Result<Self> {
+        let encoded = multibase
+            .strip_prefix(BASE58_BTC)
+            .ok_or_else(|| DidError::UnsupportedMultibase(multibase.to_string()))?;
+        let data = bs58::decode(encoded)
+            .into_vec()
+            .map_err(|_e| DidError::WrongEncoding(multibase.to_string()))?;
+        Self::from_multicodec(&data, multibase)
+    }
+
+    /// Verifies raw signature of given data. ECDSA signatures are expected
+    /// as concatenated `r` and `s` values.
+    pub fn verify(&self, data: &[u8], signature: &[u8]) -> SignatureStatus {
+        let valid = match self {
+            DidPublicKey::Ed25519(key) => ed25519_dalek::Signature::from_bytes(signature)
+                .map(|signature| key.verify(data, &signature).is_ok())
+                .ok(),
+            #[cfg(feature = "secp256k1")]
+            DidPublicKey::Secp256k1(key) => k256::ecdsa::Signature::from_slice(signature)
+                .map(|signature| {
+                    k256::ecdsa::signature::Verifier::verify(key, data, &signature).is_ok()
+                })
+                .ok(),
+            #[cfg(feature = "p256")]
+            DidPublicKey::P256(key) => p256::ecdsa::Signature::from_slice(signature)
+                .map(|signature| {
+                    p256::ecdsa::signature::Verifier::verify(key, data, &signature).is_ok()
+                })
+                .ok(),
+        };
+        match valid {
+            Some(true) => SignatureStatus::Valid,
+            _ => SignatureStatus::Invalid,
+        }
+    }
+}
+
+/// `did:key` identifier, ie. public key of controller prefixed with its
+/// multicodec code, encoded in base58btc.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DidKey(DidPublicKey);
+
+impl Identifier for DidKey {}
+
+impl DidKey {
+    pub fn public_key(&self) -> &DidPublicKey {
+        &self.0
+    }
+}
+
+impl From<DidPublicKey> for DidKey {
+    fn from(public_key: DidPublicKey) -> Self {
+        Self(public_key)
+    }
+}
+
+impl From<Ed25519Identifier> for DidKey {
+    fn from(identifier: Ed25519Identifier) -> Self {
+        Self(DidPublicKey::Ed25519(*identifier.public_key()))
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl From<crate::ecdsa::secp256k1::Secp256k1Identifier> for DidKey {
+    fn from(identifier: crate::ecdsa::secp256k1::Secp256k1Identifier) -> Self {
+        Self(DidPublicKey::Secp256k1(*identifier.public_key()))
+    }
+}
+
+#[cfg(feature = "p256")]
+impl From<crate::ecdsa::p256::P256Identifier> for DidKey {
+    fn from(identifier: crate::ecdsa::p256::P256Identifier) -> Self {
+        Self(DidPublicKey::P256(*identifier.public_key()))
+    }
+}
+
+impl fmt::Display for DidKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", DID_KEY_PREFIX, self.0.to_multibase())
+    }
+}
+
+impl FromStr for DidKey {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let multibase = s
+            .strip_prefix(DID_KEY_PREFIX)
+            .ok_or_else(|| DidError::NotDidKey(s.to_string()))?;
+        Ok(Self(DidPublicKey::from_multibase(multibase)?))
+    }
+}
+
+impl Serialize for DidKey {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DidKey {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Signature made with key of `did:key` identifier, together with the
+/// identifier. Signature is serialized as base64url multibase string.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DidKeySignature {
+    #[serde(rename = "i")]
+    signer: DidKey,
+    #[serde(rename = "s", with = "multibase_signature")]
+    signature: Vec<u8>,
+}
+
+impl DidKeySignature {
+    pub fn new(signer: DidKey, signature: Vec<u8>) -> Self {
+        Self { signer, signature }
+    }
+
+    pub fn signer(&self) -> &DidKey {
+        &self.signer
+    }
+
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+}
+
+impl From<Ed25519Signature> for DidKeySignature {
+    fn from(signature: Ed25519Signature) -> Self {
+        Self {
+            signer: signature.signer().clone().into(),
+            signature: signature.signature().to_bytes().to_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "secp256k1")]
+impl From<crate::ecdsa::secp256k1::Secp256k1Signature> for DidKeySignature {
+    fn from(signature: crate::ecdsa::secp256k1::Secp256k1Signature) -> Self {
+        Self {
+            signer: signature.signer().clone().into(),
+            signature: signature.signature().to_bytes().to_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "p256")]
+impl From<crate::ecdsa::p256::P256Signature> for DidKeySignature {
+    fn from(signature: crate::ecdsa::p256::P256Signature) -> Self {
+        Self {
+            signer: signature.signer().clone().into(),
+            signature: signature.signature().to_bytes().to_vec(),
+        }
+    }
+}
+
+impl Signature for DidKeySignature {
+    type Identifier = DidKey;
+
+    fn get_signers(&self) -> Vec<Self::Identifier> {
+        vec![self.signer.clone()]
+    }
+}
+
+pub(super) mod multibase_signature {
+    use super::*;
+
+    /// Multibase prefix of base64url encoding without padding.
+    const BASE64_URL: char = 'u';
+
+    pub fn serialize<S: Serializer>(
+        signature: &[u8],
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!(
+            "{}{}",
+            BASE64_URL,
+            URL_SAFE_NO_PAD.encode(signature)
+        ))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        encoded
+            .strip_prefix(BASE64_URL)
+            .ok_or_else(|| DidError::UnsupportedMultibase(encoded.clone()))
+            .and_then(|data| {
+                URL_SAFE_NO_PAD
+                    .decode(data)
+                    .map_err(|_e| DidError::WrongEncoding(encoded.clone()))
+            })
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Verifies signatures with keys resolved from `did:key` identifiers of
+/// their signers.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DidKeyVerifier;
+
+impl Verifier for DidKeyVerifier {
+    type Signature = DidKeySignature;
+
+    fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>> {
+        Ok(s.iter()
+            .map(|sig| sig.signer.0.verify(data, &sig.signature))
+            .collect())
+    }
+}
+
+#[test]
+fn test_did_key_encoding() -> Result<()> {
+    // Test vector of did:key specification
+    let did = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+    let identifier: DidKey = did.parse()?;
+    assert!(matches!(identifier.public_key(), DidPublicKey::Ed25519(_)));
+    assert_eq!(identifier.to_string(), did);
+    assert_eq!(serde_json::to_string(&identifier)?, format!("\"{}\"", did));
+
+    assert!(matches!(
+        "did:web:example.com".parse::<DidKey>(),
+        Err(crate::error::Error::DidError(DidError::NotDidKey(_)))
+    ));
+    assert!(matches!(
+        "did:key:u7QE".parse::<DidKey>(),
+        Err(crate::error::Error::DidError(
+            DidError::UnsupportedMultibase(_)
+        ))
+    ));
+    // X25519 keys can't sign
+    assert!(matches!(
+        "did:key:z6LSeu9HkTHSfLLeUs2nnzUSNedgDUevfNQgQjQC23ZCit6F".parse::<DidKey>(),
+        Err(crate::error::Error::DidError(DidError::UnsupportedKeyType(
+            _
+        )))
+    ));
+    Ok(())
+}
+
+/// ECDSA keys are supported only with `secp256k1` and `p256` features
+/// enabled, otherwise they are rejected as unsupported.
+#[test]
+fn test_did_key_ecdsa_encoding() -> Result<()> {
+    for (did, supported) in [
+        (
+            "did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme",
+            cfg!(feature = "secp256k1"),
+        ),
+        (
+            "did:key:zDnaerDaTF5BXEavCrfRZEk316dpbLsfPDZ3WJ5hRTPFU2169",
+            cfg!(feature = "p256"),
+        ),
+    ] {
+        if supported {
+            assert_eq!(did.parse::<DidKey>()?.to_string(), did);
+        } else {
+            assert!(matches!(
+                did.parse::<DidKey>(),
+                Err(crate::error::Error::DidError(DidError::UnsupportedKeyType(
+                    _
+                )))
+            ));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,149 @@
+//! Controlling identifiers expressed as decentralized identifiers.
+
+use std::{fmt, str::FromStr};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use self::key::{multibase_signature, DidKey, DidKeySignature};
+use crate::{Identifier, Result, Signature};
+
+pub mod key;
+pub mod resolver;
+pub mod verifier;
+
+#[derive(Error, Debug)]
+pub enum DidError {
+    #[error("{0} isn't DID")]
+    NotDid(String),
+    #[error("{0} isn't did:key identifier")]
+    NotDidKey(String),
+    #[error("Unsupported multibase encoding of {0}")]
+    UnsupportedMultibase(String),
+    #[error("Improper encoding of {0}")]
+    WrongEncoding(String),
+    #[error("Unsupported key type of {0}")]
+    UnsupportedKeyType(String),
+    #[error("Improper public key")]
+    WrongPublicKey,
+    #[error("Can't read DID documents: {0}")]
+    DocumentsUnavailable(String),
+}
+
+/// Decentralized identifier of any method, ie. `did:<method>:<id>`. Keys of
+/// methods other than `did:key` have to be resolved.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Did(String);
+
+impl Identifier for Did {}
+
+impl Did {
+    pub fn method(&self) -> &str {
+        self.0.split(':').nth(1).unwrap_or_default()
+    }
+}
+
+impl From<DidKey> for Did {
+    fn from(identifier: DidKey) -> Self {
+        Self(identifier.to_string())
+    }
+}
+
+impl fmt::Display for Did {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for Did {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.splitn(3, ':');
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some("did"), Some(method), Some(id))
+                if !method.is_empty()
+                    && method
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+                    && !id.is_empty() =>
+            {
+                Ok(Self(s.to_string()))
+            }
+            _ => Err(DidError::NotDid(s.to_string()).into()),
+        }
+    }
+}
+
+impl Serialize for Did {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Did {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Signature made with one of keys of DID together with the DID.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DidSignature {
+    #[serde(rename = "i")]
+    signer: Did,
+    #[serde(rename = "s", with = "multibase_signature")]
+    signature: Vec<u8>,
+}
+
+impl DidSignature {
+    pub fn new(signer: Did, signature: Vec<u8>) -> Self {
+        Self { signer, signature }
+    }
+
+    pub fn signer(&self) -> &Did {
+        &self.signer
+    }
+
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+}
+
+impl From<DidKeySignature> for DidSignature {
+    fn from(signature: DidKeySignature) -> Self {
+        Self {
+            signer: signature.signer().clone().into(),
+            signature: signature.signature().to_vec(),
+        }
+    }
+}
+
+impl Signature for DidSignature {
+    type Identifier = Did;
+
+    fn get_signers(&self) -> Vec<Self::Identifier> {
+        vec![self.signer.clone()]
+    }
+}
+
+#[test]
+fn test_did_parsing() -> Result<()> {
+    let did: Did = "did:web:example.com:user:alice".parse()?;
+    assert_eq!(did.method(), "web");
+    assert_eq!(did.to_string(), "did:web:example.com:user:alice");
+    for wrong in [
+        "did:web",
+        "did::example.com",
+        "did:Web:example.com",
+        "web:a:b",
+    ] {
+        assert!(matches!(
+            wrong.parse::<Did>(),
+            Err(crate::error::Error::DidError(DidError::NotDid(_)))
+        ));
+    }
+    Ok(())
+}
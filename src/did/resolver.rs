@@ -0,0 +1,190 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::{key::DidPublicKey, Did, DidError};
+use crate::Result;
+
+/// Key of DID document. Only keys encoded as `publicKeyMultibase` are
+/// supported, methods expressing keys otherwise, ie. as JWK, have none.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerificationMethod {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub method_type: String,
+    pub controller: Did,
+    #[serde(
+        rename = "publicKeyMultibase",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub public_key_multibase: Option<String>,
+}
+
+/// Part of DID document needed to verify signatures of its subject. Other
+/// properties of the document are ignored.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DidDocument {
+    pub id: Did,
+    #[serde(rename = "verificationMethod", default = "Vec::new")]
+    pub verification_method: Vec<VerificationMethod>,
+}
+
+impl DidDocument {
+    pub fn new(id: Did) -> Self {
+        Self {
+            id,
+            verification_method: Vec::new(),
+        }
+    }
+
+    /// Adds `Multikey` verification method controlled by the subject.
+    pub fn with_key(mut self, public_key: &DidPublicKey) -> Self {
+        let method = VerificationMethod {
+            id: format!("{}#key-{}", self.id, self.verification_method.len() + 1),
+            method_type: "Multikey".into(),
+            controller: self.id.clone(),
+            public_key_multibase: Some(public_key.to_multibase()),
+        };
+        self.verification_method.push(method);
+        self
+    }
+
+    /// Returns keys of verification methods which can verify signatures.
+    /// Methods without `publicKeyMultibase`, and keys of unsupported types,
+    /// are skipped.
+    pub fn public_keys(&self) -> Vec<DidPublicKey> {
+        self.verification_method
+            .iter()
+            .filter_map(|method| method.public_key_multibase.as_deref())
+            .filter_map(|multibase| DidPublicKey::from_multibase(multibase).ok())
+            .collect()
+    }
+}
+
+/// Source of current DID documents.
+pub trait Resolver {
+    /// Returns current document of given DID or `None` if it's unknown.
+    fn resolve(&self, did: &Did) -> Result<Option<DidDocument>>;
+}
+
+/// Resolver of documents kept in memory. Adding document of the same DID
+/// again replaces it, eg. after key rotation.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryResolver {
+    documents: HashMap<Did, DidDocument>,
+}
+
+impl MemoryResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_document(mut self, document: DidDocument) -> Self {
+        self.insert(document);
+        self
+    }
+
+    pub fn insert(&mut self, document: DidDocument) {
+        self.documents.insert(document.id.clone(), document);
+    }
+}
+
+impl Resolver for MemoryResolver {
+    fn resolve(&self, did: &Did) -> Result<Option<DidDocument>> {
+        Ok(self.documents.get(did).cloned())
+    }
+}
+
+/// Resolver of documents stored as JSON files in a directory. Every `.json`
+/// file is read and the DID document with matching `id` is returned, files
+/// which aren't DID documents are skipped. The directory is read on every
+/// resolution, so it can be updated while the resolver is used.
+#[derive(Clone, Debug)]
+pub struct FileResolver {
+    dir: PathBuf,
+}
+
+impl FileResolver {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+impl Resolver for FileResolver {
+    fn resolve(&self, did: &Did) -> Result<Option<DidDocument>> {
+        let unavailable = |e: std::io::Error| DidError::DocumentsUnavailable(e.to_string());
+        for entry in fs::read_dir(&self.dir).map_err(unavailable)? {
+            let path = entry.map_err(unavailable)?.path();
+            if path.extension() != Some("json".as_ref()) {
+                continue;
+            }
+            let content = fs::read(&path).map_err(unavailable)?;
+            match serde_json::from_slice::<DidDocument>(&content) {
+                Ok(document) if &document.id == did => return Ok(Some(document)),
+                _ => continue,
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[test]
+fn test_resolvers() -> Result<()> {
+    let did: Did = "did:web:example.com".parse()?;
+    let key = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+        .parse::<super::key::DidKey>()?
+        .public_key()
+        .clone();
+    let document = DidDocument::new(did.clone()).with_key(&key);
+    assert_eq!(document.public_keys(), vec![key]);
+
+    let resolver = MemoryResolver::new().with_document(document.clone());
+    assert_eq!(resolver.resolve(&did)?, Some(document.clone()));
+    assert_eq!(resolver.resolve(&"did:web:example.org".parse()?)?, None);
+
+    // Rotated document replaces the previous one
+    let rotated = DidDocument::new(did.clone());
+    let resolver = resolver.with_document(rotated.clone());
+    assert_eq!(resolver.resolve(&did)?, Some(rotated));
+
+    let dir = tempfile::tempdir().unwrap();
+    let resolver = FileResolver::new(dir.path());
+    assert_eq!(resolver.resolve(&did)?, None);
+    fs::write(dir.path().join("notes.txt"), "not a document").unwrap();
+    // JSON files other than DID documents are skipped
+    fs::write(dir.path().join("config.json"), r#"{"name": "ledger"}"#).unwrap();
+    fs::write(dir.path().join("broken.json"), "{").unwrap();
+    fs::write(
+        dir.path().join("example.json"),
+        r#"{
+            "@context": ["https://www.w3.org/ns/did/v1"],
+            "id": "did:web:example.com",
+            "verificationMethod": [{
+                "id": "did:web:example.com#key-0",
+                "type": "JsonWebKey2020",
+                "controller": "did:web:example.com",
+                "publicKeyJwk": {"kty": "OKP", "crv": "X25519", "x": "AAAA"}
+            }, {
+                "id": "did:web:example.com#key-1",
+                "type": "Multikey",
+                "controller": "did:web:example.com",
+                "publicKeyMultibase": "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"
+            }]
+        }"#,
+    )
+    .unwrap();
+    // Method expressing key as JWK has none
+    let resolved = resolver.resolve(&did)?.unwrap();
+    assert_eq!(resolved.verification_method.len(), 2);
+    assert_eq!(resolved.verification_method[0].public_key_multibase, None);
+    assert_eq!(resolved.public_keys(), document.public_keys());
+
+    assert!(matches!(
+        FileResolver::new(dir.path().join("missing")).resolve(&did),
+        Err(crate::error::Error::DidError(
+            DidError::DocumentsUnavailable(_)
+        ))
+    ));
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
+
+use said::derivation::{HashFunction, HashFunctionCode};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    key::{DidKey, DidPublicKey},
+    resolver::Resolver,
+    Did, DidError, DidSignature,
+};
+use crate::{
+    error::Error,
+    verifier::{SignatureStatus, Verifier},
+    Result,
+};
+
+/// Verifies signatures of DIDs with keys of their documents. Keys of
+/// `did:key` identifiers are taken from identifiers themselves, documents
+/// of other DIDs come from the resolver.
+///
+/// Signatures are verified against current documents, unless they sign an
+/// anchored block. Keys which verified signatures of a block when it was
+/// anchored are recorded in key states under fingerprint of the block, and
+/// the block is verified against them since then, so it stays valid after
+/// keys are rotated. Key states have to be stored along with the ledger and
+/// restored with `with_key_states` before the ledger is loaded again.
+pub struct DidVerifier<R: Resolver> {
+    resolver: R,
+    key_states: Mutex<KeyStates>,
+}
+
+impl<R: Resolver> DidVerifier<R> {
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            key_states: Mutex::new(KeyStates::new()),
+        }
+    }
+
+    /// Restores key states recorded for anchored blocks.
+    pub fn with_key_states(self, key_states: KeyStates) -> Self {
+        Self {
+            key_states: Mutex::new(key_states),
+            ..self
+        }
+    }
+
+    /// Returns key states recorded for anchored blocks so far.
+    pub fn key_states(&self) -> KeyStates {
+        self.key_states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+
+    /// Returns keys of current document of given DID. Unknown DID has none.
+    fn current_keys(&self, did: &Did) -> Result<Vec<DidPublicKey>> {
+        if did.method() == "key" {
+            let identifier: DidKey = did.to_string().parse()?;
+            return Ok(vec![identifier.public_key().clone()]);
+        }
+        Ok(self
+            .resolver
+            .resolve(did)?
+            .map(|document| document.public_keys())
+            .unwrap_or_default())
+    }
+
+    fn recorded_key(&self, fingerprint: &str, did: &Did) -> Option<DidPublicKey> {
+        self.key_states
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(fingerprint, did)
+            .cloned()
+    }
+}
+
+/// Keys which verified signatures of anchored blocks, by fingerprints of
+/// the blocks and signers. Keys of `did:key` identifiers can't change, so
+/// they aren't recorded.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyStates(HashMap<String, HashMap<Did, DidKey>>);
+
+impl KeyStates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn get(&self, fingerprint: &str, did: &Did) -> Option<&DidPublicKey> {
+        self.0
+            .get(fingerprint)?
+            .get(did)
+            .map(|identifier| identifier.public_key())
+    }
+
+    fn insert(&mut self, fingerprint: String, did: Did, key: DidPublicKey) {
+        self.0
+            .entry(fingerprint)
+            .or_default()
+            .insert(did, key.into());
+    }
+}
+
+/// Fingerprint of signed data, under which keys of its signers are
+/// recorded.
+fn fingerprint(data: &[u8]) -> String {
+    HashFunction::from(HashFunctionCode::Blake3_256)
+        .derive(data)
+        .to_string()
+}
+
+impl<R: Resolver> Verifier for DidVerifier<R> {
+    type Signature = DidSignature;
+
+    /// Signature is valid if any key of its signer verifies it. Signers
+    /// which can't be resolved, or have no keys, are reported as unknown,
+    /// `did:key` identifiers of keys which can't sign as unsupported.
+    fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>> {
+        let fingerprint = fingerprint(data);
+        Ok(s.iter()
+            .map(|sig| {
+                let keys = match self.recorded_key(&fingerprint, sig.signer()) {
+                    Some(key) => vec![key],
+                    None => match self.current_keys(sig.signer()) {
+                        Ok(keys) if !keys.is_empty() => keys,
+                        Err(Error::DidError(DidError::UnsupportedKeyType(_))) => {
+                            return SignatureStatus::UnsupportedType
+                        }
+                        _ => return SignatureStatus::UnknownKey,
+                    },
+                };
+                if keys
+                    .iter()
+                    .any(|key| key.verify(data, sig.signature()).is_valid())
+                {
+                    SignatureStatus::Valid
+                } else {
+                    SignatureStatus::Invalid
+                }
+            })
+            .collect())
+    }
+
+    /// Records current keys which verify accepted signatures.
+    fn accept(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<()> {
+        let fingerprint = fingerprint(data);
+        for sig in s {
+            if sig.signer().method() == "key"
+                || self.recorded_key(&fingerprint, sig.signer()).is_some()
+            {
+                continue;
+            }
+            let key = self
+                .current_keys(sig.signer())?
+                .into_iter()
+                .find(|key| key.verify(data, sig.signature()).is_valid());
+            if let Some(key) = key {
+                self.key_states
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(fingerprint.clone(), sig.signer().clone(), key);
+            }
+        }
+        Ok(())
+    }
+}
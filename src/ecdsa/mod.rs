@@ -0,0 +1,181 @@
+//! ECDSA identifiers, signatures and verifiers. Identifiers are compressed
+//! public keys of controllers and signatures are made over SHA-256 digest
+//! of signed data.
+
+/// Generates identifier, signature and verifier types of ECDSA over curve
+/// implemented by given RustCrypto crate. Public keys and signatures are
+/// encoded as CESR primitives of given derivation codes.
+macro_rules! ecdsa_backend {
+    (
+        $curve:ident,
+        $name:literal,
+        $public_key_code:literal,
+        $signature_code:literal,
+        $identifier:ident,
+        $signature:ident,
+        $verifier:ident
+    ) => {
+        use std::{fmt, str::FromStr};
+
+        use ::$curve::ecdsa::{
+            signature::{Signer, Verifier as _},
+            Signature as EcdsaSignature, SigningKey, VerifyingKey,
+        };
+        use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+        use crate::{
+            primitive::{decode, encode, PrimitiveError},
+            verifier::{SignatureStatus, Verifier},
+            Identifier, Result, Signature,
+        };
+
+        #[doc = concat!("CESR derivation code of compressed ", $name, " public key.")]
+        const PUBLIC_KEY_CODE: &str = $public_key_code;
+        #[doc = concat!("CESR derivation code of ", $name, " ECDSA signature.")]
+        const SIGNATURE_CODE: &str = $signature_code;
+
+        #[doc = concat!(
+            "Identifier being ", $name, " public key of its controller. It's encoded as\n",
+            "CESR primitive, ie. `", $public_key_code, "` followed by base64 encoded compressed key."
+        )]
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub struct $identifier(VerifyingKey);
+
+        impl Identifier for $identifier {}
+
+        impl $identifier {
+            pub fn public_key(&self) -> &VerifyingKey {
+                &self.0
+            }
+        }
+
+        impl From<VerifyingKey> for $identifier {
+            fn from(public_key: VerifyingKey) -> Self {
+                Self(public_key)
+            }
+        }
+
+        impl fmt::Display for $identifier {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&encode(
+                    PUBLIC_KEY_CODE,
+                    self.0.to_encoded_point(true).as_bytes(),
+                ))
+            }
+        }
+
+        impl FromStr for $identifier {
+            type Err = crate::error::Error;
+
+            fn from_str(s: &str) -> Result<Self> {
+                let raw = decode(PUBLIC_KEY_CODE, 33, s)?;
+                let public_key = VerifyingKey::from_sec1_bytes(&raw)
+                    .map_err(|_e| PrimitiveError::WrongPublicKey)?;
+                Ok(Self(public_key))
+            }
+        }
+
+        impl Serialize for $identifier {
+            fn serialize<S: Serializer>(
+                &self,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $identifier {
+            fn deserialize<D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<Self, D::Error> {
+                String::deserialize(deserializer)?
+                    .parse()
+                    .map_err(de::Error::custom)
+            }
+        }
+
+        #[doc = concat!($name, " ECDSA signature together with identifier of its signer.")]
+        #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+        pub struct $signature {
+            #[serde(rename = "i")]
+            signer: $identifier,
+            #[serde(rename = "s", with = "cesr_signature")]
+            signature: EcdsaSignature,
+        }
+
+        impl $signature {
+            pub fn new(signer: $identifier, signature: EcdsaSignature) -> Self {
+                Self { signer, signature }
+            }
+
+            /// Signs data with given key. Its public key identifies the signer.
+            pub fn sign(signing_key: &SigningKey, data: &[u8]) -> Self {
+                Self {
+                    signer: $identifier(*signing_key.verifying_key()),
+                    signature: signing_key.sign(data),
+                }
+            }
+
+            pub fn signer(&self) -> &$identifier {
+                &self.signer
+            }
+
+            pub fn signature(&self) -> &EcdsaSignature {
+                &self.signature
+            }
+        }
+
+        impl Signature for $signature {
+            type Identifier = $identifier;
+
+            fn get_signers(&self) -> Vec<Self::Identifier> {
+                vec![self.signer.clone()]
+            }
+        }
+
+        mod cesr_signature {
+            use super::*;
+
+            pub fn serialize<S: Serializer>(
+                signature: &EcdsaSignature,
+                serializer: S,
+            ) -> std::result::Result<S::Ok, S::Error> {
+                serializer.serialize_str(&encode(SIGNATURE_CODE, &signature.to_bytes()))
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> std::result::Result<EcdsaSignature, D::Error> {
+                let encoded = String::deserialize(deserializer)?;
+                let raw = decode(SIGNATURE_CODE, 64, &encoded).map_err(de::Error::custom)?;
+                EcdsaSignature::from_slice(&raw)
+                    .map_err(|_e| de::Error::custom(PrimitiveError::WrongSignature))
+            }
+        }
+
+        #[doc = concat!(
+            "Verifies ", $name, " ECDSA signatures. Identifiers of signers are their\n",
+            "public keys, so no key lookup is needed."
+        )]
+        #[derive(Clone, Copy, Debug, Default)]
+        pub struct $verifier;
+
+        impl Verifier for $verifier {
+            type Signature = $signature;
+
+            fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>> {
+                Ok(s.iter()
+                    .map(|sig| match sig.signer.0.verify(data, &sig.signature) {
+                        Ok(()) => SignatureStatus::Valid,
+                        Err(_) => SignatureStatus::Invalid,
+                    })
+                    .collect())
+            }
+        }
+    };
+}
+
+#[cfg(feature = "p256")]
+pub mod p256;
+#[cfg(feature = "secp256k1")]
+pub mod secp256k1;
@@ -0,0 +1,36 @@
+ecdsa_backend!(
+    p256,
+    "P-256",
+    "1AAJ",
+    "0I",
+    P256Identifier,
+    P256Signature,
+    P256Verifier
+);
+
+#[test]
+fn test_p256() -> Result<()> {
+    let signing_key = SigningKey::from_slice(&[7; 32]).unwrap();
+    let identifier = P256Identifier::from(*signing_key.verifying_key());
+    // Known answer, computed independently for private key of seven bytes.
+    let encoded = identifier.to_string();
+    assert_eq!(encoded, "1AAJAx4YUy_UdUwC8wQdnHXOszuD_9gax85P6ILMscmLxYlu");
+    assert_eq!(encoded.parse::<P256Identifier>()?, identifier);
+
+    // Signatures are deterministic (RFC 6979).
+    let signature = P256Signature::sign(&signing_key, b"data");
+    let serialized = serde_json::to_string(&signature)?;
+    assert_eq!(
+        serialized,
+        r#"{"i":"1AAJAx4YUy_UdUwC8wQdnHXOszuD_9gax85P6ILMscmLxYlu","s":"0IBsyWAlFv7Ig8Plx5PgoBYheuaRMJ8Zf78csjwVF8ANTsOrnkvy6g7DPQ1mdrEWTwkZAjaA5EfUF0Hz55UQa_q4"}"#
+    );
+    let deserialized: P256Signature = serde_json::from_str(&serialized)?;
+    assert_eq!(deserialized, signature);
+    assert_eq!(deserialized.get_signers(), vec![identifier]);
+
+    let statuses = P256Verifier.verify(b"data", vec![signature.clone()])?;
+    assert_eq!(statuses, vec![SignatureStatus::Valid]);
+    let statuses = P256Verifier.verify(b"other data", vec![signature])?;
+    assert_eq!(statuses, vec![SignatureStatus::Invalid]);
+    Ok(())
+}
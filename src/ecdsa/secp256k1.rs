@@ -0,0 +1,36 @@
+ecdsa_backend!(
+    k256,
+    "secp256k1",
+    "1AAB",
+    "0C",
+    Secp256k1Identifier,
+    Secp256k1Signature,
+    Secp256k1Verifier
+);
+
+#[test]
+fn test_secp256k1() -> Result<()> {
+    let signing_key = SigningKey::from_slice(&[7; 32]).unwrap();
+    let identifier = Secp256k1Identifier::from(*signing_key.verifying_key());
+    // Known answer, computed independently for private key of seven bytes.
+    let encoded = identifier.to_string();
+    assert_eq!(encoded, "1AABApicC3bLVjlx_cm-8x7AbDVg8ySdbunl2DxXYlWW4F9v");
+    assert_eq!(encoded.parse::<Secp256k1Identifier>()?, identifier);
+
+    // Signatures are deterministic (RFC 6979).
+    let signature = Secp256k1Signature::sign(&signing_key, b"data");
+    let serialized = serde_json::to_string(&signature)?;
+    assert_eq!(
+        serialized,
+        r#"{"i":"1AABApicC3bLVjlx_cm-8x7AbDVg8ySdbunl2DxXYlWW4F9v","s":"0CArZKogvRAgRTa1y3dzxDiL_1RxDhWK57l93cj6jnH_-WK7oHTkgRwq9B4jmBOHh94Ty0YiXVDGWpsd3kw87A1m"}"#
+    );
+    let deserialized: Secp256k1Signature = serde_json::from_str(&serialized)?;
+    assert_eq!(deserialized, signature);
+    assert_eq!(deserialized.get_signers(), vec![identifier]);
+
+    let statuses = Secp256k1Verifier.verify(b"data", vec![signature.clone()])?;
+    assert_eq!(statuses, vec![SignatureStatus::Valid]);
+    let statuses = Secp256k1Verifier.verify(b"other data", vec![signature])?;
+    assert_eq!(statuses, vec![SignatureStatus::Invalid]);
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use std::{fmt, str::FromStr};
+
+use ed25519_dalek::{Keypair, PublicKey, Signer, Verifier as _};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    primitive::{decode, encode, PrimitiveError},
+    verifier::{SignatureStatus, Verifier},
+    Identifier, Result, Signature,
+};
+
+/// CESR derivation code of Ed25519 public key.
+const PUBLIC_KEY_CODE: &str = "D";
+/// CESR derivation code of Ed25519 signature.
+const SIGNATURE_CODE: &str = "0B";
+
+/// Identifier being Ed25519 public key of its controller. It's encoded as
+/// CESR primitive, ie. `D` followed by base64 encoded key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Ed25519Identifier(PublicKey);
+
+impl Identifier for Ed25519Identifier {}
+
+impl Ed25519Identifier {
+    pub fn public_key(&self) -> &PublicKey {
+        &self.0
+    }
+}
+
+impl From<PublicKey> for Ed25519Identifier {
+    fn from(public_key: PublicKey) -> Self {
+        Self(public_key)
+    }
+}
+
+impl fmt::Display for Ed25519Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode(PUBLIC_KEY_CODE, self.0.as_bytes()))
+    }
+}
+
+impl FromStr for Ed25519Identifier {
+    type Err = crate::error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let raw = decode(PUBLIC_KEY_CODE, 32, s)?;
+        let public_key =
+            PublicKey::from_bytes(&raw).map_err(|_e| PrimitiveError::WrongPublicKey)?;
+        Ok(Self(public_key))
+    }
+}
+
+impl Serialize for Ed25519Identifier {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ed25519Identifier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// Ed25519 signature together with identifier of its signer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ed25519Signature {
+    #[serde(rename = "i")]
+    signer: Ed25519Identifier,
+    #[serde(rename = "s", with = "cesr_signature")]
+    signature: ed25519_dalek::Signature,
+}
+
+impl Ed25519Signature {
+    pub fn new(signer: Ed25519Identifier, signature: ed25519_dalek::Signature) -> Self {
+        Self { signer, signature }
+    }
+
+    /// Signs data with given keypair. Its public key identifies the signer.
+    pub fn sign(keypair: &Keypair, data: &[u8]) -> Self {
+        Self {
+            signer: Ed25519Identifier(keypair.public),
+            signature: keypair.sign(data),
+        }
+    }
+
+    pub fn signer(&self) -> &Ed25519Identifier {
+        &self.signer
+    }
+
+    pub fn signature(&self) -> &ed25519_dalek::Signature {
+        &self.signature
+    }
+}
+
+impl Signature for Ed25519Signature {
+    type Identifier = Ed25519Identifier;
+
+    fn get_signers(&self) -> Vec<Self::Identifier> {
+        vec![self.signer.clone()]
+    }
+}
+
+mod cesr_signature {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        signature: &ed25519_dalek::Signature,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode(SIGNATURE_CODE, &signature.to_bytes()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<ed25519_dalek::Signature, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        let raw = decode(SIGNATURE_CODE, 64, &encoded).map_err(de::Error::custom)?;
+        ed25519_dalek::Signature::from_bytes(&raw)
+            .map_err(|_e| de::Error::custom(PrimitiveError::WrongSignature))
+    }
+}
+
+/// Verifies Ed25519 signatures. Identifiers of signers are their public
+/// keys, so no key lookup is needed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Ed25519Verifier;
+
+impl Verifier for Ed25519Verifier {
+    type Signature = Ed25519Signature;
+
+    fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>> {
+        Ok(s.iter()
+            .map(|sig| match sig.signer.0.verify(data, &sig.signature) {
+                Ok(()) => SignatureStatus::Valid,
+                Err(_) => SignatureStatus::Invalid,
+            })
+            .collect())
+    }
+}
+
+#[test]
+fn test_ed25519_encoding() -> Result<()> {
+    let encoded = "DEsSdlPeweh0IACH9lGlp-EL_g-e3kqEu5IlFa4Zy4ec";
+    let identifier: Ed25519Identifier = encoded.parse()?;
+    assert_eq!(identifier.to_string(), encoded);
+    assert_eq!(
+        serde_json::to_string(&identifier)?,
+        format!("\"{}\"", encoded)
+    );
+    assert!("EEsSdlPeweh0IACH9lGlp-EL_g-e3kqEu5IlFa4Zy4ec"
+        .parse::<Ed25519Identifier>()
+        .is_err());
+    assert!("DEsSdlPeweh0IACH9lGlp"
+        .parse::<Ed25519Identifier>()
+        .is_err());
+
+    let keypair = Keypair::generate(&mut rand::rngs::OsRng {});
+    let signature = Ed25519Signature::sign(&keypair, b"data");
+    let serialized = serde_json::to_string(&signature)?;
+    assert!(serialized.contains(r#""s":"0B"#));
+    let deserialized: Ed25519Signature = serde_json::from_str(&serialized)?;
+    assert_eq!(deserialized, signature);
+    assert_eq!(deserialized.get_signers(), vec![keypair.public.into()]);
+
+    let statuses = Ed25519Verifier.verify(b"data", vec![signature.clone()])?;
+    assert_eq!(statuses, vec![SignatureStatus::Valid]);
+    let statuses = Ed25519Verifier.verify(b"other data", vec![signature])?;
+    assert_eq!(statuses, vec![SignatureStatus::Invalid]);
+    Ok(())
+}
@@ -1,8 +1,15 @@
-#[cfg(feature = "keri")]
+#[cfg(feature = "keriox")]
 use keri::prefix::error::Error as PrefixError;
 use thiserror::Error;
 
-use crate::{block::BlockError, microledger::MicroledgerError};
+#[cfg(feature = "did")]
+use crate::did::DidError;
+#[cfg(any(feature = "ed25519", feature = "secp256k1", feature = "p256"))]
+use crate::primitive::PrimitiveError;
+use crate::{
+    block::BlockError, config::ConfigError, limits::LimitError, microledger::MicroledgerError,
+    version::VersionError,
+};
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -12,6 +19,15 @@ pub enum Error {
     #[error("{0}")]
     BlockError(#[from] BlockError),
 
+    #[error(transparent)]
+    ConfigError(#[from] ConfigError),
+
+    #[error(transparent)]
+    VersionError(#[from] VersionError),
+
+    #[error(transparent)]
+    LimitError(#[from] LimitError),
+
     #[error("{0}")]
     SealError(String),
 
@@ -21,10 +37,27 @@ pub enum Error {
     #[error("Can't encode element")]
     EncodeError(#[from] serde_json::Error),
 
-    #[cfg(feature = "keri")]
+    #[error("Can't encode or decode CBOR element")]
+    CborError(#[from] serde_cbor::Error),
+
+    #[error("Can't encode MessagePack element")]
+    MgpkEncodeError(#[from] rmp_serde::encode::Error),
+
+    #[error("Can't decode MessagePack element")]
+    MgpkDecodeError(#[from] rmp_serde::decode::Error),
+
+    #[cfg(feature = "keriox")]
     #[error(transparent)]
     BasicPrefixError(#[from] PrefixError),
 
+    #[cfg(any(feature = "ed25519", feature = "secp256k1", feature = "p256"))]
+    #[error(transparent)]
+    PrimitiveError(#[from] PrimitiveError),
+
+    #[cfg(feature = "did")]
+    #[error(transparent)]
+    DidError(#[from] DidError),
+
     #[error("Can't parse cesr stream")]
     CesrError,
 
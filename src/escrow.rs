@@ -0,0 +1,166 @@
+use chrono::{DateTime, Duration, Utc};
+use said::SelfAddressingIdentifier;
+use serde::Serialize;
+
+use crate::{
+    block::SignedBlock,
+    limits::{LimitError, Limits},
+    partially_signed_block::PartiallySignedBlock,
+    Identifier, Result, Signature,
+};
+
+/// Reason why block is held in escrow instead of being anchored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowReason {
+    /// Some of preceding blocks haven't arrived yet.
+    MissingPrevious,
+    /// Not enough controlling identifiers signed the block yet.
+    MissingSignatures,
+}
+
+/// Block waiting in escrow, together with signatures collected so far.
+#[derive(Clone, Debug)]
+pub struct EscrowedBlock<I, S>
+where
+    I: Identifier + Serialize + Clone,
+    S: Signature<Identifier = I>,
+{
+    pub block: PartiallySignedBlock<I, S>,
+    pub reason: EscrowReason,
+    /// Time the block was put in escrow. Signatures added later don't
+    /// extend it.
+    pub received: DateTime<Utc>,
+}
+
+/// Blocks which can't be anchored yet, because their predecessors or
+/// signatures are missing. Blocks are dropped if they wait longer than
+/// escrow timeout.
+#[derive(Clone, Debug)]
+pub struct Escrow<I, S>
+where
+    I: Identifier + Serialize + Clone,
+    S: Signature<Identifier = I>,
+{
+    entries: Vec<EscrowedBlock<I, S>>,
+    timeout: Duration,
+}
+
+impl<I, S> Default for Escrow<I, S>
+where
+    I: Identifier + Serialize + Clone,
+    S: Signature<Identifier = I>,
+{
+    fn default() -> Self {
+        Self {
+            entries: vec![],
+            timeout: Duration::hours(1),
+        }
+    }
+}
+
+impl<I, S> Escrow<I, S>
+where
+    I: Identifier + Serialize + Clone + PartialEq,
+    S: Serialize + Clone + Signature<Identifier = I>,
+{
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            entries: vec![],
+            timeout,
+        }
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn entries(&self) -> &[EscrowedBlock<I, S>] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns escrowed block of given fingerprint.
+    pub fn get(&self, fingerprint: &SelfAddressingIdentifier) -> Option<&EscrowedBlock<I, S>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.block.block.digital_fingerprint.as_ref() == Some(fingerprint))
+    }
+
+    /// Puts block in escrow. If the block is already escrowed, its new
+    /// signatures are added to the collected ones, which are kept for
+    /// signers who already signed it, so signatures have to be verified
+    /// beforehand. Block is rejected if collected signatures would exceed
+    /// attachment size limit. If escrow is full, new block is rejected, so
+    /// flood of blocks can't push out the ones already waiting.
+    pub fn insert(
+        &mut self,
+        signed_block: SignedBlock<I, S>,
+        reason: EscrowReason,
+        now: DateTime<Utc>,
+        limits: &Limits,
+    ) -> Result<()> {
+        let fingerprint = signed_block.block.get_fingerprint()?;
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| entry.block.block.digital_fingerprint.as_ref() == Some(&fingerprint));
+        let mut block = match position {
+            Some(position) => self.entries[position].block.clone(),
+            None => PartiallySignedBlock::new(signed_block.block),
+        };
+        for signature in signed_block.signatures {
+            block.add_signature(signature);
+        }
+        limits.check_attachments(block.signatures())?;
+        match position {
+            Some(position) => {
+                let entry = &mut self.entries[position];
+                entry.block = block;
+                entry.reason = reason;
+            }
+            None => {
+                if self.entries.len() >= limits.max_escrowed_blocks {
+                    return Err(LimitError::EscrowFull {
+                        max: limits.max_escrowed_blocks,
+                    }
+                    .into());
+                }
+                self.entries.push(EscrowedBlock {
+                    block,
+                    reason,
+                    received: now,
+                })
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes block of given fingerprint from escrow.
+    pub fn remove(
+        &mut self,
+        fingerprint: &SelfAddressingIdentifier,
+    ) -> Option<EscrowedBlock<I, S>> {
+        let position = self.entries.iter().position(|entry| {
+            entry.block.block.digital_fingerprint.as_ref() == Some(fingerprint)
+        })?;
+        Some(self.entries.remove(position))
+    }
+
+    /// Removes and returns blocks which waited longer than escrow timeout.
+    pub fn prune(&mut self, now: DateTime<Utc>) -> Vec<EscrowedBlock<I, S>> {
+        let timeout = self.timeout;
+        let (expired, entries) = self
+            .entries
+            .drain(..)
+            .partition(|entry| entry.received + timeout < now);
+        self.entries = entries;
+        expired
+    }
+}
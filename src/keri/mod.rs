@@ -1,9 +1,16 @@
-use std::{convert::TryInto, sync::Arc};
+use std::sync::Arc;
 
-use keri::prefix::IdentifierPrefix;
+use keri::{event_message::signature::Nontransferable, prefix::IdentifierPrefix};
 
+use self::signed_block::{
+    parse_message, parse_receipts, parse_signatures, AttachmentMessage, MessageType,
+};
 use crate::{
-    error::Error, microledger::MicroLedger, verifier::Verifier, Identifier, Result, Signature,
+    block::{BlockError, SignedBlock},
+    limits::Limits,
+    microledger::{MicroLedger, MicroledgerError},
+    verifier::Verifier,
+    Identifier, Result, Signature,
 };
 
 pub mod signed_block;
@@ -18,8 +25,50 @@ impl Identifier for IdentifierPrefix {}
 impl Signature for KeriSignature {
     type Identifier = IdentifierPrefix;
 
-    fn get_signer(&self) -> Option<Self::Identifier> {
-        self.get_signer()
+    /// Every couplet of nontransferable signature has its own signer.
+    /// Indexed signatures don't identify their signers.
+    fn get_signers(&self) -> Vec<Self::Identifier> {
+        match self {
+            KeriSignature::NonTransferable(Nontransferable::Couplet(couplets)) => couplets
+                .iter()
+                .map(|(signer, _signature)| IdentifierPrefix::Basic(signer.clone()))
+                .collect(),
+            _ => self.get_signer().into_iter().collect(),
+        }
+    }
+
+    /// Indexed nontransferable signatures are resolved into couplets. Indexes
+    /// have to point to basic prefixes.
+    fn resolve(&self, controlling_identifiers: &[IdentifierPrefix]) -> Result<Option<Self>> {
+        resolve_indexed(self, |index| controlling_identifiers.get(index))
+    }
+}
+
+/// Resolves indexed nontransferable signature into couplets, looking up
+/// controlling identifiers by indexes of signatures.
+pub(crate) fn resolve_indexed<'a>(
+    signature: &KeriSignature,
+    controlling_identifier: impl Fn(usize) -> Option<&'a IdentifierPrefix>,
+) -> Result<Option<KeriSignature>> {
+    match signature {
+        KeriSignature::NonTransferable(Nontransferable::Indexed(signatures)) => {
+            let couplets = signatures
+                .iter()
+                .map(|signature| {
+                    let index = signature.index.current() as usize;
+                    match controlling_identifier(index) {
+                        Some(IdentifierPrefix::Basic(signer)) => {
+                            Ok((signer.clone(), signature.signature.clone()))
+                        }
+                        _ => Err(BlockError::WrongSignatureIndex(index).into()),
+                    }
+                })
+                .collect::<Result<_>>()?;
+            Ok(Some(KeriSignature::NonTransferable(
+                Nontransferable::Couplet(couplets),
+            )))
+        }
+        _ => Ok(None),
     }
 }
 
@@ -28,19 +77,106 @@ where
     V: Verifier<Signature = KeriSignature>,
 {
     pub fn new_from_cesr(stream: &[u8], verifier: Arc<V>) -> Result<Self> {
-        let (_rest, parsed_stream) = cesrox::parse_many(stream).map_err(|_e| Error::CesrError)?;
-        let mut microledger = MicroLedger::new(verifier);
-        parsed_stream
-            .into_iter()
-            .try_for_each(|pd| microledger.anchor(pd.try_into()?))?;
+        Self::new_from_cesr_with_limits(stream, verifier, Limits::default())
+    }
+
+    /// Parses microledger from CESR stream, verifying receipts against given
+    /// witnesses.
+    pub fn new_from_cesr_with_witnesses(
+        stream: &[u8],
+        verifier: Arc<V>,
+        witnesses: Vec<IdentifierPrefix>,
+        threshold: usize,
+    ) -> Result<Self> {
+        let mut microledger = MicroLedger::new(verifier).with_witnesses(witnesses, threshold)?;
+        microledger.extend_from_cesr(stream)?;
+        Ok(microledger)
+    }
+
+    /// Parses microledger from CESR stream. Size of every block and its
+    /// attachments is checked before the block is decoded.
+    pub fn new_from_cesr_with_limits(
+        stream: &[u8],
+        verifier: Arc<V>,
+        limits: Limits,
+    ) -> Result<Self> {
+        let mut microledger = MicroLedger::new(verifier).with_limits(limits);
+        microledger.extend_from_cesr(stream)?;
         Ok(microledger)
     }
 
+    /// Anchors blocks from CESR stream, checking them against limits of the
+    /// ledger. Endorsements and receipts are added to already anchored
+    /// blocks. Receipts are verified if ledger witnesses are set, otherwise
+    /// they are kept unverified, as when ledger is loaded from JSON. Stream opened
+    /// by ledger identifier has to continue this ledger, or start it. Blocks
+    /// were accepted by the ledger they are exported from, so their validity
+    /// windows aren't checked against current time.
+    pub fn extend_from_cesr(&mut self, stream: &[u8]) -> Result<()> {
+        let mut ledger_id = None;
+        let mut rest = stream;
+        while !rest.is_empty() {
+            let (new_rest, parsed) = parse_message(rest, self.limits())?;
+            rest = new_rest;
+            match AttachmentMessage::from_payload(&parsed.payload)? {
+                Some(AttachmentMessage {
+                    message_type: MessageType::Ledger,
+                    block,
+                    ..
+                }) => {
+                    if matches!(self.id(), Some(id) if *id != block) {
+                        return Err(MicroledgerError::WrongLedger.into());
+                    }
+                    ledger_id = Some(block);
+                }
+                Some(AttachmentMessage {
+                    message_type: MessageType::Endorsement,
+                    block,
+                    ..
+                }) => {
+                    for endorsement in parse_signatures(parsed.attachments)? {
+                        self.endorse(&block, endorsement)?;
+                    }
+                }
+                Some(AttachmentMessage {
+                    message_type: MessageType::Receipt,
+                    block,
+                    ..
+                }) => {
+                    let receipts = parse_receipts(parsed.attachments)?;
+                    self.limits().check_attachments(&receipts)?;
+                    for receipt in receipts {
+                        if self.witnesses().is_empty() {
+                            self.store_receipt(&block, receipt)?;
+                        } else {
+                            self.add_receipt(&block, receipt)?;
+                        }
+                    }
+                }
+                None => {
+                    let signed_block = SignedBlock::from_parsed(parsed, self.limits())?;
+                    if self.id().is_none()
+                        && ledger_id.is_some()
+                        && signed_block.block.digital_fingerprint != ledger_id
+                    {
+                        return Err(MicroledgerError::WrongLedger.into());
+                    }
+                    self.anchor_block(signed_block, false)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes microledger, starting with its identifier.
     pub fn to_cesr(&self) -> Result<Vec<u8>> {
-        Ok(self
-            .blocks
-            .iter()
-            .flat_map(|bl| bl.to_cesr().unwrap())
-            .collect())
+        let mut cesr = match self.id() {
+            Some(id) => AttachmentMessage::new(MessageType::Ledger, id.clone())?.to_cesr(&[])?,
+            None => vec![],
+        };
+        for signed_block in &self.blocks {
+            cesr.extend(signed_block.to_cesr()?);
+        }
+        Ok(cesr)
     }
 }
@@ -1,31 +1,246 @@
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
 
-use cesrox::{parse, payload::Payload, ParsedData};
+use cesrox::{group::Group, parse, payload::Payload, ParsedData};
 use keri::event_message::signature::{get_signatures, signatures_into_groups};
 use keri::prefix::IdentifierPrefix;
+use said::SelfAddressingIdentifier;
+use serde::{Deserialize, Serialize};
 
-use crate::block::Block;
+use crate::block::{Block, BlockError};
 use crate::error::Error;
-use crate::Result;
-use crate::{block::SignedBlock, Encode};
+use crate::limits::{LimitError, Limits};
+use crate::receipt::Receipt;
+use crate::{
+    block::SignedBlock,
+    version::{SerializationKind, Version, VersionError},
+    Encode,
+};
+use crate::{Result, Signature};
 
 use super::KeriSignature;
 
+/// Type of message in CESR stream, other than a block.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum MessageType {
+    /// Opens exported microledger, referring to its genesis block.
+    #[serde(rename = "led")]
+    Ledger,
+    #[serde(rename = "end")]
+    Endorsement,
+    #[serde(rename = "rct")]
+    Receipt,
+}
+
+/// Checks if data is serialized message, which starts with version string
+/// followed by message type. Blocks have controlling identifiers right after
+/// version string, so messages are told apart from blocks without decoding
+/// them.
+fn is_message(data: &[u8]) -> bool {
+    let (version_start, type_start) = (br#"{"v":""#, br#"","t":"#);
+    let type_offset = version_start.len() + "MICR10JSON000000_".len();
+    data.starts_with(version_start)
+        && data.get(type_offset..type_offset + type_start.len()) == Some(&type_start[..])
+}
+
+/// Message carrying endorsements or witness receipts of already anchored
+/// block. It refers to the block by its fingerprint, so the block stays
+/// untouched. Exported microledger starts with message referring to its
+/// genesis block, which fingerprint is the ledger identifier.
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct AttachmentMessage {
+    #[serde(rename = "v")]
+    pub version: Version,
+    #[serde(rename = "t")]
+    pub message_type: MessageType,
+    #[serde(rename = "d")]
+    pub block: SelfAddressingIdentifier,
+}
+
+impl AttachmentMessage {
+    pub(crate) fn new(message_type: MessageType, block: SelfAddressingIdentifier) -> Result<Self> {
+        let message = Self {
+            version: Version::new(SerializationKind::Json, 0),
+            message_type,
+            block,
+        };
+        let size = serde_json::to_vec(&message)?.len();
+        Ok(Self {
+            version: message.version.clone().with_size(size)?,
+            ..message
+        })
+    }
+
+    /// Returns attachment message carried by given payload, if it isn't a
+    /// block.
+    pub(crate) fn from_payload(payload: &Payload) -> Result<Option<Self>> {
+        let data = match payload {
+            Payload::JSON(data) => data,
+            Payload::CBOR(_) | Payload::MGPK(_) => return Ok(None),
+        };
+        if !is_message(data) {
+            return Ok(None);
+        }
+        let message: Self = serde_json::from_slice(data)?;
+        message.version.check(data.len())?;
+        if message.version.kind != SerializationKind::Json {
+            return Err(VersionError::WrongKind {
+                declared: message.version.kind.code().to_string(),
+                actual: SerializationKind::Json.code().to_string(),
+            }
+            .into());
+        }
+        Ok(Some(message))
+    }
+
+    pub(crate) fn to_cesr(&self, signatures: &[KeriSignature]) -> Result<Vec<u8>> {
+        ParsedData {
+            payload: Payload::JSON(serde_json::to_vec(self)?),
+            attachments: signatures_into_groups(signatures),
+        }
+        .to_cesr()
+        .map_err(|_e| Error::CesrError)
+    }
+}
+
+/// Parses next message of CESR stream. Parser is given at most the maximal
+/// message size, so oversized message is rejected before it's parsed whole.
+/// Returns rest of the stream with parsed message.
+pub(crate) fn parse_message<'a>(
+    stream: &'a [u8],
+    limits: &Limits,
+) -> Result<(&'a [u8], ParsedData)> {
+    let window = &stream[..stream.len().min(limits.max_message_size())];
+    let (rest, parsed) = parse(window).map_err(|_e| -> Error {
+        if window.len() < stream.len() {
+            LimitError::MessageTooLarge {
+                max: limits.max_message_size(),
+            }
+            .into()
+        } else {
+            Error::CesrError
+        }
+    })?;
+    let payload_size = match &parsed.payload {
+        Payload::JSON(data) | Payload::CBOR(data) | Payload::MGPK(data) => data.len(),
+    };
+    limits.check_block_size(payload_size)?;
+    Ok((&stream[window.len() - rest.len()..], parsed))
+}
+
+pub(crate) fn parse_signatures(groups: Vec<Group>) -> Result<Vec<KeriSignature>> {
+    Ok(groups
+        .into_iter()
+        .map(|g| get_signatures(g).map_err(|_e| Error::CesrError))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect())
+}
+
+/// Parses witness receipts. Witnesses are identified by their signatures.
+pub(crate) fn parse_receipts(
+    groups: Vec<Group>,
+) -> Result<Vec<Receipt<IdentifierPrefix, KeriSignature>>> {
+    parse_signatures(groups)?
+        .into_iter()
+        .map(|signature| {
+            let witness = signature
+                .get_signers()
+                .into_iter()
+                .next()
+                .ok_or(BlockError::UnattributableSignatures)?;
+            Ok(Receipt::new(witness, signature))
+        })
+        .collect()
+}
+
 impl SignedBlock<IdentifierPrefix, KeriSignature> {
+    /// Serializes block with its signatures. Endorsements and receipts follow
+    /// in separate messages.
     pub fn to_cesr(&self) -> Result<Vec<u8>> {
-        let payload = Payload::JSON(Encode::encode(&self.block)?);
+        let encoded = Encode::encode(&self.block)?;
+        let payload = match self.block.serialization_kind() {
+            SerializationKind::Json | SerializationKind::Jcs => Payload::JSON(encoded),
+            SerializationKind::Cbor => Payload::CBOR(encoded),
+            SerializationKind::Mgpk => Payload::MGPK(encoded),
+        };
         let groups = signatures_into_groups(&self.signatures);
 
         let d = ParsedData {
             payload,
             attachments: groups,
         };
-        d.to_cesr().map_err(|_e| Error::CesrError)
+        let mut cesr = d.to_cesr().map_err(|_e| Error::CesrError)?;
+        if !self.endorsements.is_empty() {
+            let endorsements =
+                AttachmentMessage::new(MessageType::Endorsement, self.block.get_fingerprint()?)?;
+            cesr.extend(endorsements.to_cesr(&self.endorsements)?);
+        }
+        if !self.receipts.is_empty() {
+            let receipts =
+                AttachmentMessage::new(MessageType::Receipt, self.block.get_fingerprint()?)?;
+            let signatures: Vec<_> = self
+                .receipts
+                .iter()
+                .map(|receipt| receipt.signature.clone())
+                .collect();
+            cesr.extend(receipts.to_cesr(&signatures)?);
+        }
+        Ok(cesr)
     }
 
+    /// Parses block with its signatures, and endorsements and receipts
+    /// following it.
     pub fn from_cesr(stream: &[u8]) -> Result<Self> {
-        let (_rest, parsed) = parse(stream).map_err(|_e| Error::CesrError)?;
-        parsed.try_into()
+        Self::from_cesr_with_limits(stream, &Limits::default())
+    }
+
+    /// Parses block like `from_cesr`, checking sizes of the block and its
+    /// attachments against limits.
+    pub fn from_cesr_with_limits(stream: &[u8], limits: &Limits) -> Result<Self> {
+        let (mut rest, parsed) = parse_message(stream, limits)?;
+        let mut signed_block = Self::from_parsed(parsed, limits)?;
+        let fingerprint = signed_block.block.get_fingerprint()?;
+        while let Ok((new_rest, parsed)) = parse_message(rest, limits) {
+            match AttachmentMessage::from_payload(&parsed.payload)? {
+                Some(AttachmentMessage {
+                    message_type: MessageType::Endorsement,
+                    block,
+                    ..
+                }) if block == fingerprint => {
+                    signed_block
+                        .endorsements
+                        .extend(parse_signatures(parsed.attachments)?);
+                    limits.check_attachments(&signed_block.endorsements)?;
+                }
+                Some(AttachmentMessage {
+                    message_type: MessageType::Receipt,
+                    block,
+                    ..
+                }) if block == fingerprint => {
+                    signed_block
+                        .receipts
+                        .extend(parse_receipts(parsed.attachments)?);
+                    limits.check_attachments(&signed_block.receipts)?;
+                }
+                _ => break,
+            }
+            rest = new_rest;
+        }
+        Ok(signed_block)
+    }
+
+    /// Decodes block of parsed message and its signatures, checking them
+    /// against limits.
+    pub(crate) fn from_parsed(parsed: ParsedData, limits: &Limits) -> Result<Self> {
+        let block: Block<IdentifierPrefix> = match parsed.payload {
+            Payload::JSON(data) | Payload::CBOR(data) | Payload::MGPK(data) => {
+                Block::from_slice_with_limits(&data, limits)?
+            }
+        };
+        let signatures = parse_signatures(parsed.attachments)?;
+        limits.check_attachments(&signatures)?;
+        Ok(block.to_signed_block(signatures))
     }
 }
 
@@ -33,17 +248,7 @@ impl TryFrom<ParsedData> for SignedBlock<IdentifierPrefix, KeriSignature> {
     type Error = Error;
 
     fn try_from(parsed: ParsedData) -> std::result::Result<Self, Self::Error> {
-        let block: Block<IdentifierPrefix> = match parsed.payload {
-            Payload::JSON(json) => serde_json::from_slice(&json).map_err(Error::EncodeError)?,
-            Payload::CBOR(_) => todo!(),
-            Payload::MGPK(_) => todo!(),
-        };
-        let signatures: Vec<_> = parsed
-            .attachments
-            .into_iter()
-            .flat_map(|g| get_signatures(g).unwrap())
-            .collect();
-        Ok(block.to_signed_block(signatures))
+        Self::from_parsed(parsed, &Limits::default())
     }
 }
 
@@ -64,10 +269,17 @@ mod test {
     use said::derivation::{HashFunction, HashFunctionCode};
     use tempfile::Builder;
 
+    use cesrox::payload::Payload;
+
+    use super::{AttachmentMessage, MessageType};
     use crate::{
         block::{Block, SignedBlock},
-        keri::KeriSignature,
+        keri::{
+            tests::{keri_verifier, Controller},
+            KeriSignature,
+        },
         seals::Seal,
+        version::SerializationKind,
         Encode, Result,
     };
 
@@ -93,7 +305,7 @@ mod test {
         };
         let seal = HashFunction::from(HashFunctionCode::Blake3_256).derive("exmaple".as_bytes());
         let prev = HashFunction::from(HashFunctionCode::Blake3_256).derive("exmaple".as_bytes());
-        let block = Block::new(vec![Seal::Attached(seal)], Some(prev), vec![(bp)]);
+        let block = Block::new(vec![Seal::Attached(seal)], Some(prev), vec![(bp)])?;
 
         let sig = KeriSignature::NonTransferable(Nontransferable::Couplet(vec![(
             pref,
@@ -112,4 +324,80 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_signed_block_metadata() -> Result<()> {
+        let (_root, validator) = keri_verifier();
+        let controller = Controller::generate();
+        let seal = HashFunction::from(HashFunctionCode::Blake3_256).derive(b"example");
+        let block = Block::new(
+            vec![Seal::Attached(seal)],
+            None,
+            vec![controller.identifier()],
+        )?
+        .with_metadata("schema", &"EBdXt3gIXOf2BBWNHdSXCJnFJL5OuQPyM5K0neuniccM")?;
+        let signature = controller.sign(&block.encode()?);
+        let signed = block.to_signed_block(vec![signature]);
+        assert!(signed.verify(validator)?);
+
+        let block_from_cesr =
+            SignedBlock::<IdentifierPrefix, KeriSignature>::from_cesr(&signed.to_cesr()?)?;
+        assert_eq!(block_from_cesr.block, signed.block);
+        assert_eq!(
+            block_from_cesr.block.metadata["schema"],
+            "EBdXt3gIXOf2BBWNHdSXCJnFJL5OuQPyM5K0neuniccM"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_signed_block_serialization_kinds() -> Result<()> {
+        let (_root, validator) = keri_verifier();
+        let controller = Controller::generate();
+        let seal = HashFunction::from(HashFunctionCode::Blake3_256).derive(b"example");
+        for kind in [SerializationKind::Cbor, SerializationKind::Mgpk] {
+            let block = Block::new(
+                vec![Seal::Attached(seal.clone())],
+                None,
+                vec![controller.identifier()],
+            )?
+            .with_serialization_kind(kind)?;
+            let signature = controller.sign(&block.encode()?);
+            let signed = block.to_signed_block(vec![signature]);
+            assert!(signed.verify(validator.clone())?);
+
+            let block_from_cesr =
+                SignedBlock::<IdentifierPrefix, KeriSignature>::from_cesr(&signed.to_cesr()?)?;
+            assert_eq!(block_from_cesr.block, signed.block);
+            assert_eq!(block_from_cesr.signatures, signed.signatures);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_attachment_message() -> Result<()> {
+        let fingerprint = HashFunction::from(HashFunctionCode::Blake3_256).derive(b"block");
+        let message = AttachmentMessage::new(MessageType::Receipt, fingerprint.clone())?;
+        let encoded = serde_json::to_vec(&message)?;
+        assert_eq!(
+            String::from_utf8(encoded.clone()).unwrap(),
+            format!(
+                r#"{{"v":"MICR10JSON000056_","t":"rct","d":"{}"}}"#,
+                fingerprint
+            )
+        );
+        let parsed = AttachmentMessage::from_payload(&Payload::JSON(encoded.clone()))?.unwrap();
+        assert_eq!(parsed.message_type, MessageType::Receipt);
+        assert_eq!(parsed.block, fingerprint);
+
+        // Blocks aren't taken for messages
+        let block: Block<IdentifierPrefix> = Block::new(vec![], None, vec![])?;
+        assert!(AttachmentMessage::from_payload(&Payload::JSON(block.encode()?))?.is_none());
+        // and messages of improper version are rejected.
+        let resized = String::from_utf8(encoded)
+            .unwrap()
+            .replace("MICR10JSON000056_", "MICR10JSON000057_");
+        assert!(AttachmentMessage::from_payload(&Payload::JSON(resized.into_bytes())).is_err());
+        Ok(())
+    }
 }
@@ -1,26 +1,81 @@
 use std::sync::Arc;
 
 use cesrox::primitives::codes::self_signing::SelfSigning;
+use chrono::{DateTime, Duration, Utc};
 use ed25519_dalek::ExpandedSecretKey;
 use keri::{
     database::SledEventDatabase,
     event_message::signature::Nontransferable,
     keys::PublicKey,
-    prefix::{BasicPrefix, IdentifierPrefix, SelfSigningPrefix},
+    prefix::{BasicPrefix, IdentifierPrefix, IndexedSignature, SelfSigningPrefix},
     processor::basic_processor::BasicProcessor,
 };
 use rand::rngs::OsRng;
 use said::SelfAddressingIdentifier;
-use tempfile::Builder;
+use tempfile::{Builder, TempDir};
 
 use crate::{
-    block::Block,
+    block::{Block, BlockError, SignedBlock},
+    clock::Clock,
+    error::Error,
     keri::{verifier::KeriVerifier, KeriSignature},
-    microledger::MicroLedger,
+    limits::{LimitError, Limits},
+    microledger::{MicroLedger, MicroledgerError},
+    receipt::Receipt,
     seal_bundle::{SealBundle, SealData},
     Encode, Result,
 };
 
+/// Returns verifier backed by KERI database in temporary directory, which
+/// is removed when returned directory is dropped.
+pub(super) fn keri_verifier() -> (TempDir, Arc<KeriVerifier>) {
+    let root = Builder::new().prefix("test-db").tempdir().unwrap();
+    let db = Arc::new(SledEventDatabase::new(root.path()).unwrap());
+    (root, Arc::new(KeriVerifier::new(db)))
+}
+
+/// Controller identified by basic prefix of its Ed25519 key.
+pub(super) struct Controller {
+    keypair: ed25519_dalek::Keypair,
+    pub(super) prefix: BasicPrefix,
+}
+
+impl Controller {
+    pub(super) fn generate() -> Self {
+        let keypair = ed25519_dalek::Keypair::generate(&mut OsRng {});
+        let prefix = BasicPrefix::Ed25519(PublicKey::new(keypair.public.as_bytes().to_vec()));
+        Self { keypair, prefix }
+    }
+
+    pub(super) fn identifier(&self) -> IdentifierPrefix {
+        IdentifierPrefix::Basic(self.prefix.clone())
+    }
+
+    fn raw_sign(&self, data: &[u8]) -> SelfSigningPrefix {
+        let signature = ExpandedSecretKey::from(&self.keypair.secret)
+            .sign(data, &self.keypair.public)
+            .as_ref()
+            .to_vec();
+        SelfSigningPrefix::new(SelfSigning::Ed25519Sha512, signature)
+    }
+
+    /// Signs data with signature naming its signer.
+    pub(super) fn sign(&self, data: &[u8]) -> KeriSignature {
+        KeriSignature::NonTransferable(Nontransferable::Couplet(vec![(
+            self.prefix.clone(),
+            self.raw_sign(data),
+        )]))
+    }
+
+    /// Signs data with signature pointing to its signer by position in
+    /// controlling identifiers.
+    pub(super) fn sign_indexed(&self, data: &[u8], index: u16) -> KeriSignature {
+        KeriSignature::NonTransferable(Nontransferable::Indexed(vec![
+            IndexedSignature::new_both_same(self.raw_sign(data), index),
+        ]))
+    }
+}
+
 #[test]
 fn test_microledger() -> Result<()> {
     let root = Builder::new().prefix("test-db").tempdir().unwrap();
@@ -84,6 +139,173 @@ fn test_microledger() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_indexed_signatures() -> Result<()> {
+    let (_root, validator) = keri_verifier();
+    let controller = Controller::generate();
+
+    let mut microledger = MicroLedger::new(validator);
+    let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+    let block = microledger.pre_anchor_block(vec![controller.identifier()], &seals)?;
+    let encoded = block.encode()?;
+
+    assert!(matches!(
+        microledger.anchor(
+            block
+                .clone()
+                .to_signed_block(vec![controller.sign_indexed(&encoded, 1)])
+        ),
+        Err(Error::BlockError(BlockError::WrongSignatureIndex(1)))
+    ));
+
+    // Indexed signature is resolved into couplet of controller prefix for
+    // verification, and stored as submitted.
+    let signature = controller.sign_indexed(&encoded, 0);
+    microledger.anchor(block.clone().to_signed_block(vec![signature.clone()]))?;
+    assert_eq!(microledger.blocks[0].signatures, vec![signature]);
+    assert!(matches!(
+        &microledger.get_resolved_signatures(&block.get_fingerprint()?)?[0],
+        KeriSignature::NonTransferable(Nontransferable::Couplet(couplets)) if couplets[0].0 == controller.prefix
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_endorsements() -> Result<()> {
+    let (_root, validator) = keri_verifier();
+    let (controller, endorser) = (Controller::generate(), Controller::generate());
+
+    let mut microledger = MicroLedger::new(validator.clone());
+    let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+    let block = microledger.pre_anchor_block(vec![controller.identifier()], &seals)?;
+    let encoded = block.encode()?;
+    let fingerprint = block.get_fingerprint()?;
+    microledger.anchor(block.to_signed_block(vec![controller.sign(&encoded)]))?;
+    microledger.endorse(&fingerprint, endorser.sign(&encoded))?;
+
+    // Endorsements follow endorsed block in CESR stream
+    let parsed = MicroLedger::<KeriSignature, _, _>::new_from_cesr(
+        &microledger.to_cesr()?,
+        validator.clone(),
+    )?;
+    assert_eq!(parsed.blocks.len(), 1);
+    assert_eq!(
+        parsed.get_endorsements(&fingerprint)?,
+        microledger.get_endorsements(&fingerprint)?
+    );
+    assert_eq!(parsed.blocks[0].block.get_fingerprint()?, fingerprint);
+    assert_eq!(parsed.id(), microledger.id());
+
+    // Exported ledger starts with its identifier, so it can't be taken for
+    // other ledger.
+    let mut other = MicroLedger::new(validator);
+    let block = other.pre_anchor_block(vec![endorser.identifier()], &seals)?;
+    let signature = endorser.sign(&block.encode()?);
+    other.anchor(block.to_signed_block(vec![signature]))?;
+    assert!(matches!(
+        other.extend_from_cesr(&microledger.to_cesr()?),
+        Err(Error::MicroError(MicroledgerError::WrongLedger))
+    ));
+    Ok(())
+}
+
+/// Clock lagging two days behind system time.
+struct LaggingClock;
+
+impl Clock for LaggingClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now() - Duration::days(2)
+    }
+}
+
+#[test]
+fn test_reload_expired_blocks() -> Result<()> {
+    let (_root, validator) = keri_verifier();
+    let controller = Controller::generate();
+
+    // Block valid for one day, which ended yesterday
+    let mut microledger = MicroLedger::new(validator.clone()).with_clock(Arc::new(LaggingClock));
+    let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+    let block = microledger
+        .pre_anchor_block(vec![controller.identifier()], &seals)?
+        .with_validity(None, Some(LaggingClock.now() + Duration::days(1)))?;
+    let signature = controller.sign(&block.encode()?);
+    microledger.anchor(block.to_signed_block(vec![signature]))?;
+
+    // is still loaded from exported ledger.
+    let parsed =
+        MicroLedger::<KeriSignature, _, _>::new_from_cesr(&microledger.to_cesr()?, validator)?;
+    assert_eq!(parsed.blocks.len(), 1);
+    assert_eq!(parsed.id(), microledger.id());
+    Ok(())
+}
+
+#[test]
+fn test_witness_receipts() -> Result<()> {
+    let (_root, validator) = keri_verifier();
+    let (controller, witness_controller) = (Controller::generate(), Controller::generate());
+    let witness = witness_controller.identifier();
+
+    let mut microledger =
+        MicroLedger::new(validator.clone()).with_witnesses(vec![witness.clone()], 1)?;
+    let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+    let block = microledger.pre_anchor_block(vec![controller.identifier()], &seals)?;
+    let fingerprint = block.get_fingerprint()?;
+    let signature = controller.sign(&block.encode()?);
+    microledger.anchor(block.to_signed_block(vec![signature]))?;
+    let receipt = Receipt::new(
+        witness.clone(),
+        witness_controller.sign(fingerprint.to_string().as_bytes()),
+    );
+    assert!(microledger.add_receipt(&fingerprint, receipt)?);
+    assert!(microledger.is_witnessed(&fingerprint)?);
+
+    // Receipts follow receipted block in CESR stream
+    let cesr = microledger.to_cesr()?;
+    let parsed = MicroLedger::new_from_cesr_with_witnesses(
+        &cesr,
+        validator.clone(),
+        vec![witness.clone()],
+        1,
+    )?;
+    assert!(parsed.is_witnessed(&fingerprint)?);
+    assert_eq!(
+        parsed.get_receipts(&fingerprint)?,
+        microledger.get_receipts(&fingerprint)?
+    );
+    // and are kept unverified if witnesses aren't known.
+    let parsed = MicroLedger::<KeriSignature, _, _>::new_from_cesr(&cesr, validator.clone())?;
+    assert_eq!(
+        parsed.get_receipts(&fingerprint)?,
+        microledger.get_receipts(&fingerprint)?
+    );
+    assert_eq!(parsed.to_cesr()?, cesr);
+    // Such receipts count only once they verify against witnesses set later.
+    let unverified = parsed.with_witnesses(vec![witness.clone()], 1)?;
+    assert!(unverified.is_witnessed(&fingerprint)?);
+    let unverified = unverified.with_witnesses(vec![controller.identifier()], 1)?;
+    assert!(!unverified.is_witnessed(&fingerprint)?);
+    let mut forged = MicroLedger::<KeriSignature, _, _>::new_from_cesr(&cesr, validator.clone())?;
+    forged.blocks[0].receipts.clear();
+    forged.store_receipt(
+        &fingerprint,
+        Receipt::new(
+            witness.clone(),
+            controller.sign(fingerprint.to_string().as_bytes()),
+        ),
+    )?;
+    let forged = forged.with_witnesses(vec![witness.clone()], 1)?;
+    assert!(!forged.is_witnessed(&fingerprint)?);
+
+    // Receipts of other witnesses are rejected.
+    let other_witness = controller.identifier();
+    assert!(matches!(
+        MicroLedger::new_from_cesr_with_witnesses(&cesr, validator, vec![other_witness], 1),
+        Err(Error::MicroError(MicroledgerError::UnknownWitness))
+    ));
+    Ok(())
+}
+
 #[test]
 fn test_microledger_traversing() -> Result<()> {
     let root = Builder::new().prefix("test-db").tempdir().unwrap();
@@ -123,3 +345,112 @@ fn test_microledger_traversing() -> Result<()> {
     // assert_eq!(seals[0], "one more message");
     Ok(())
 }
+
+#[test]
+fn test_parsing_limits() -> Result<()> {
+    let (_root, validator) = keri_verifier();
+
+    let serialized_microledger = r#"{"ci":["DEsSdlPeweh0IACH9lGlp-EL_g-e3kqEu5IlFa4Zy4ec"],"d":"EGd6asvSLN8kfMfnOMu87-wVzq0YiS7SLEqBBbKFBYOG","s":["AEOqPFj2zhoKSXkSRxeWNS7NQbvjBTreKhukIxWJKZyAP"]}-CABDEsSdlPeweh0IACH9lGlp-EL_g-e3kqEu5IlFa4Zy4ec0BDGUXkeZdDbVqYZ75AwalK32eiirA4Cr7FIDQTNw3q516uUoq4ijEpKiwhntsuROTp4qEip6JHsy2BAxn0Jn6gO{"ci":["DEsSdlPeweh0IACH9lGlp-EL_g-e3kqEu5IlFa4Zy4ec"],"d":"EASZwg_zixnbrxNogPpgYXh9nauPBqG4jiNVn3cYFTT_","p":"EGd6asvSLN8kfMfnOMu87-wVzq0YiS7SLEqBBbKFBYOG","s":["AEOqPFj2zhoKSXkSRxeWNS7NQbvjBTreKhukIxWJKZyAP"]}-CABDEsSdlPeweh0IACH9lGlp-EL_g-e3kqEu5IlFa4Zy4ec0BC_Nz5biJZ2AweeAjD-Frk9niFKGzkpCVSUbcP5v-KGApeD_458aMzmexwI2bJ5EAGNGGUBcOIgGffq-iXJvhID{"ci":["DEsSdlPeweh0IACH9lGlp-EL_g-e3kqEu5IlFa4Zy4ec"],"d":"EL-nsW-tAdE0ex7Wm9B-V3J-ilrWOXiAt-r-8713pOHO","p":"EASZwg_zixnbrxNogPpgYXh9nauPBqG4jiNVn3cYFTT_","s":["AEOqPFj2zhoKSXkSRxeWNS7NQbvjBTreKhukIxWJKZyAP"]}-CABDEsSdlPeweh0IACH9lGlp-EL_g-e3kqEu5IlFa4Zy4ec0BCmrKe9ue57z2i9MOZrN0kMiOww2j-Tre31GKJKojYtA0pCueaXpfgt_c-BnyeFtN-vhdRo6sqfLSuJYKKmuIQF"#;
+
+    let limits = Limits {
+        max_block_size: 100,
+        ..Limits::default()
+    };
+    assert!(matches!(
+        MicroLedger::<KeriSignature, _, _>::new_from_cesr_with_limits(
+            serialized_microledger.as_bytes(),
+            validator.clone(),
+            limits,
+        ),
+        Err(Error::LimitError(LimitError::BlockTooLarge {
+            max: 100,
+            ..
+        }))
+    ));
+
+    let limits = Limits {
+        max_attachment_size: 100,
+        ..Limits::default()
+    };
+    assert!(matches!(
+        MicroLedger::<KeriSignature, _, _>::new_from_cesr_with_limits(
+            serialized_microledger.as_bytes(),
+            validator.clone(),
+            limits,
+        ),
+        Err(Error::LimitError(LimitError::AttachmentTooLarge {
+            max: 100,
+            ..
+        }))
+    ));
+
+    // Parser doesn't get more than one message of maximal size.
+    let limits = Limits {
+        max_block_size: 100,
+        max_attachment_size: 100,
+        ..Limits::default()
+    };
+    let oversized = format!(r#"{{"ci":[],"d":"{}","s":[]}}"#, "E".repeat(300));
+    assert!(matches!(
+        MicroLedger::<KeriSignature, _, _>::new_from_cesr_with_limits(
+            oversized.as_bytes(),
+            validator,
+            limits.clone(),
+        ),
+        Err(Error::LimitError(LimitError::MessageTooLarge { max: 200 }))
+    ));
+    assert!(matches!(
+        SignedBlock::<IdentifierPrefix, KeriSignature>::from_cesr_with_limits(
+            serialized_microledger.as_bytes(),
+            &limits
+        ),
+        Err(Error::LimitError(LimitError::BlockTooLarge {
+            max: 100,
+            ..
+        }))
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "ed25519")]
+#[test]
+fn test_composite_controllers() -> Result<()> {
+    use crate::{
+        composite::{CompositeIdentifier, CompositeSignature, CompositeVerifier},
+        ed25519::{Ed25519Identifier, Ed25519Signature},
+    };
+
+    let (_root, keri_verifier) = keri_verifier();
+    let verifier = CompositeVerifier::new().with_keri(keri_verifier);
+
+    let ed_keypair = ed25519_dalek::Keypair::generate(&mut OsRng {});
+    let controller = Controller::generate();
+    let controllers: Vec<CompositeIdentifier> = vec![
+        Ed25519Identifier::from(ed_keypair.public).into(),
+        controller.identifier().into(),
+    ];
+    let sign = |data: &[u8], index| -> Vec<CompositeSignature> {
+        vec![
+            Ed25519Signature::sign(&ed_keypair, data).into(),
+            controller.sign_indexed(data, index).into(),
+        ]
+    };
+
+    let mut microledger = MicroLedger::new(Arc::new(verifier));
+    let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+    let block = microledger.pre_anchor_block(controllers, &seals)?;
+    let encoded = block.encode()?;
+
+    // Index has to point to KERI prefix
+    assert!(matches!(
+        microledger.anchor(block.clone().to_signed_block(sign(&encoded, 0))),
+        Err(Error::BlockError(BlockError::WrongSignatureIndex(0)))
+    ));
+    microledger.anchor(block.clone().to_signed_block(sign(&encoded, 1)))?;
+    assert!(matches!(
+        &microledger.get_resolved_signatures(&block.get_fingerprint()?)?[1],
+        CompositeSignature::Keri(KeriSignature::NonTransferable(Nontransferable::Couplet(couplets)))
+            if couplets[0].0 == controller.prefix
+    ));
+    Ok(())
+}
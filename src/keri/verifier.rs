@@ -1,8 +1,14 @@
 use std::sync::Arc;
 
-use keri::{database::SledEventDatabase, processor::validator::EventValidator};
+use keri::{
+    database::SledEventDatabase, error::Error as KeriError,
+    event_message::signature::Nontransferable, processor::validator::EventValidator,
+};
 
-use crate::{verifier::Verifier, Result};
+use crate::{
+    verifier::{SignatureStatus, Verifier},
+    Result,
+};
 
 use super::KeriSignature;
 
@@ -11,8 +17,21 @@ pub struct KeriVerifier(EventValidator);
 impl Verifier for KeriVerifier {
     type Signature = KeriSignature;
 
-    fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<bool> {
-        Ok(s.into_iter().all(|sig| self.0.verify(data, &sig).is_ok()))
+    fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>> {
+        Ok(s.iter()
+            .map(|sig| match sig {
+                // Indexed signatures can't be verified without list of keys.
+                KeriSignature::NonTransferable(Nontransferable::Indexed(_)) => {
+                    SignatureStatus::UnsupportedType
+                }
+                _ => match self.0.verify(data, sig) {
+                    Ok(()) => SignatureStatus::Valid,
+                    Err(KeriError::SignatureVerificationError) => SignatureStatus::Invalid,
+                    // Key state of the signer couldn't be established.
+                    Err(_) => SignatureStatus::UnknownKey,
+                },
+            })
+            .collect())
     }
 }
 
@@ -1,13 +1,39 @@
 use error::Error;
 
 pub mod block;
+pub mod block_builder;
+pub mod canonical;
+pub mod clock;
+#[cfg(any(
+    feature = "ed25519",
+    feature = "secp256k1",
+    feature = "p256",
+    feature = "did",
+    feature = "keriox"
+))]
+pub mod composite;
+pub mod config;
+#[cfg(feature = "did")]
+pub mod did;
+#[cfg(any(feature = "secp256k1", feature = "p256"))]
+pub mod ecdsa;
+#[cfg(feature = "ed25519")]
+pub mod ed25519;
 pub mod error;
+pub mod escrow;
 #[cfg(feature = "keriox")]
 pub mod keri;
+pub mod limits;
 pub mod microledger;
+pub mod partially_signed_block;
+pub mod policy;
+#[cfg(any(feature = "ed25519", feature = "secp256k1", feature = "p256"))]
+pub mod primitive;
+pub mod receipt;
 pub mod seal_bundle;
 pub mod seals;
 pub mod verifier;
+pub mod version;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -20,7 +46,19 @@ pub trait Encode {
 /// Controlling identifiers can be anything that is considered identifiable within given network,
 /// ie. `Public Key`, `DID`, `KERI` prefix and so on.
 pub trait Identifier {}
+/// Signature value can be made by several signers, ie. multisig group of
+/// signatures. Each of them is validated and counted toward thresholds.
 pub trait Signature {
     type Identifier;
-    fn get_signer(&self) -> Option<Self::Identifier>;
+    fn get_signers(&self) -> Vec<Self::Identifier>;
+
+    /// Converts signature referring to its signers by their positions in
+    /// given list of controlling identifiers into one naming them. Returns
+    /// `None` for signatures which don't use positions.
+    fn resolve(&self, _controlling_identifiers: &[Self::Identifier]) -> Result<Option<Self>>
+    where
+        Self: Sized,
+    {
+        Ok(None)
+    }
 }
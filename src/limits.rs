@@ -0,0 +1,117 @@
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{block::Block, Encode, Identifier, Result};
+
+#[derive(Error, Debug)]
+pub enum LimitError {
+    #[error("Block of size {size} exceeds limit of {max} bytes")]
+    BlockTooLarge { size: usize, max: usize },
+    #[error("Block has {count} seals, while limit is {max}")]
+    TooManySeals { count: usize, max: usize },
+    #[error("Block has {count} controlling identifiers, while limit is {max}")]
+    TooManyControllers { count: usize, max: usize },
+    #[error("Attachment of size {size} exceeds limit of {max} bytes")]
+    AttachmentTooLarge { size: usize, max: usize },
+    #[error("Message exceeds limit of {max} bytes")]
+    MessageTooLarge { max: usize },
+    #[error("Escrow already holds limit of {max} blocks")]
+    EscrowFull { max: usize },
+}
+
+/// Bounds on size of blocks accepted by microledger. They protect against
+/// resource exhaustion by oversized blocks, both when parsing and anchoring.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximal size of encoded block, in bytes.
+    pub max_block_size: usize,
+    /// Maximal number of seals in a block.
+    pub max_seals: usize,
+    /// Maximal number of controlling identifiers in a block.
+    pub max_controlling_identifiers: usize,
+    /// Maximal size of block attachments, ie. signatures, in bytes. They are
+    /// measured serialized as JSON, whatever format they were received in.
+    pub max_attachment_size: usize,
+    /// Maximal number of blocks waiting in escrow. New blocks are rejected
+    /// until some of waiting ones are anchored or time out.
+    pub max_escrowed_blocks: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_block_size: 1 << 20,
+            max_seals: 1024,
+            max_controlling_identifiers: 256,
+            max_attachment_size: 1 << 20,
+            max_escrowed_blocks: 1024,
+        }
+    }
+}
+
+impl Limits {
+    /// Checks size of encoded block and numbers of its seals and controlling
+    /// identifiers.
+    pub fn check_block<I: Identifier + Serialize + Clone>(&self, block: &Block<I>) -> Result<()> {
+        self.check_counts(block)?;
+        self.check_block_size(block.encode()?.len())
+    }
+
+    /// Checks numbers of seals and controlling identifiers of the block.
+    pub(crate) fn check_counts<I: Identifier + Serialize + Clone>(
+        &self,
+        block: &Block<I>,
+    ) -> Result<()> {
+        if block.seals.len() > self.max_seals {
+            return Err(LimitError::TooManySeals {
+                count: block.seals.len(),
+                max: self.max_seals,
+            }
+            .into());
+        }
+        if block.controlling_identifiers.len() > self.max_controlling_identifiers {
+            return Err(LimitError::TooManyControllers {
+                count: block.controlling_identifiers.len(),
+                max: self.max_controlling_identifiers,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn check_block_size(&self, size: usize) -> Result<()> {
+        if size > self.max_block_size {
+            Err(LimitError::BlockTooLarge {
+                size,
+                max: self.max_block_size,
+            }
+            .into())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn check_attachment_size(&self, size: usize) -> Result<()> {
+        if size > self.max_attachment_size {
+            Err(LimitError::AttachmentTooLarge {
+                size,
+                max: self.max_attachment_size,
+            }
+            .into())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks size of signatures, endorsements or receipts attached to a
+    /// block.
+    pub fn check_attachments<A: Serialize>(&self, attachments: &[A]) -> Result<()> {
+        self.check_attachment_size(serde_json::to_vec(attachments)?.len())
+    }
+
+    /// Maximal size of raw message carrying block with its attachments. It
+    /// bounds data handed to parser, before anything is decoded.
+    pub fn max_message_size(&self) -> usize {
+        self.max_block_size.saturating_add(self.max_attachment_size)
+    }
+}
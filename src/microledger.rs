@@ -1,38 +1,231 @@
-use std::sync::Arc;
+use std::{convert::TryFrom, fmt, sync::Arc};
 
+use chrono::{DateTime, Duration, Utc};
 use said::SelfAddressingIdentifier;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::clock::{Clock, SystemClock};
+use crate::config::{ConfigError, LedgerConfig};
+use crate::error::Error;
+use crate::escrow::{Escrow, EscrowReason, EscrowedBlock};
+use crate::limits::Limits;
+use crate::policy::BlockPolicy;
+use crate::receipt::Receipt;
 use crate::seal_bundle::SealBundle;
-use crate::verifier::Verifier;
+use crate::seals::Seal;
+use crate::verifier::{first_failure, SignatureStatus, Verifier};
+use crate::version::SerializationKind;
 use crate::{
-    block::{Block, SignedBlock},
+    block::{Block, BlockError, SignedBlock},
     Result,
 };
-use crate::{Identifier, Signature};
+use crate::{Encode, Identifier, Signature};
 
 #[derive(Error, Debug)]
 pub enum MicroledgerError {
     #[error("No block of given fingerprint: {0}")]
     MissingBlock(SelfAddressingIdentifier),
+    #[error("No block of given sequence number: {0}")]
+    MissingSequenceNumber(u64),
+    #[error("Unexpected block sequence number: {0}")]
+    WrongSequenceNumber(u64),
     #[error("Block doesn't match")]
     WrongBlock,
     #[error("Signatures doesn't match controlling identifier")]
     WrongSigner,
+    #[error("Block belongs to other microledger")]
+    WrongLedger,
+    #[error("Block doesn't carry microledger identifier")]
+    MissingLedgerId,
+    #[error("Block doesn't carry sequence number")]
+    UnnumberedBlock,
+    #[error("Microledger was terminated in block {0}")]
+    Terminated(SelfAddressingIdentifier),
+    #[error("Block timestamp is earlier than previous block one")]
+    TimestampBeforePrevious,
+    #[error("Block timestamp is in the future")]
+    TimestampInFuture,
+    #[error("Block validity window hasn't started yet")]
+    NotYetValid,
+    #[error("Block validity window has already ended")]
+    Expired,
+    #[error("Block violates ledger policy: {0}")]
+    PolicyViolation(String),
+    #[error("Endorsement doesn't match the block")]
+    WrongEndorsement,
+    #[error("Receipt is issued by unknown witness")]
+    UnknownWitness,
+    #[error("Receipt doesn't match the block")]
+    WrongReceipt,
+    #[error("Signature {index} failed verification: {status}")]
+    InvalidSignature {
+        index: usize,
+        status: SignatureStatus,
+    },
 }
 
-#[derive(Default, Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize)]
+#[serde(
+    try_from = "SerializedMicroLedger<I, S>",
+    bound(
+        deserialize = "S: Deserialize<'de> + Clone, V: Default, I: Deserialize<'de> + PartialEq"
+    )
+)]
 pub struct MicroLedger<S, V, I>
 where
     S: Serialize + Signature<Identifier = I>,
     V: Verifier<Signature = S>,
     I: Identifier + Serialize + Clone,
 {
+    #[serde(rename = "i", default, skip_serializing_if = "Option::is_none")]
+    id: Option<SelfAddressingIdentifier>,
     #[serde(rename = "bs")]
     pub blocks: Vec<SignedBlock<I, S>>,
     #[serde(skip)]
     pub verifier: Arc<V>,
+    #[serde(skip, default = "default_clock")]
+    clock: Arc<dyn Clock>,
+    #[serde(skip, default = "Duration::zero")]
+    max_clock_skew: Duration,
+    #[serde(skip)]
+    policies: Vec<Arc<dyn BlockPolicy<I>>>,
+    #[serde(skip)]
+    limits: Limits,
+    #[serde(skip)]
+    escrow: Escrow<I, S>,
+    #[serde(skip)]
+    witnesses: Vec<I>,
+    #[serde(skip)]
+    witness_threshold: usize,
+}
+
+/// Microledger as it's serialized. Its identifier is derived from genesis
+/// block when it's loaded, and checked against identifiers carried by other
+/// blocks.
+#[derive(Deserialize)]
+pub struct SerializedMicroLedger<I, S>
+where
+    I: Identifier + Serialize + Clone,
+    S: Serialize + Signature<Identifier = I>,
+{
+    #[serde(rename = "i", default)]
+    id: Option<SelfAddressingIdentifier>,
+    #[serde(rename = "bs")]
+    blocks: Vec<SignedBlock<I, S>>,
+}
+
+impl<S, V, I> TryFrom<SerializedMicroLedger<I, S>> for MicroLedger<S, V, I>
+where
+    S: Serialize + Clone + Signature<Identifier = I>,
+    V: Verifier<Signature = S> + Default,
+    I: Identifier + Serialize + Clone + PartialEq,
+{
+    type Error = Error;
+
+    fn try_from(ledger: SerializedMicroLedger<I, S>) -> Result<Self> {
+        let id = ledger
+            .blocks
+            .first()
+            .map(|genesis| genesis.block.get_fingerprint())
+            .transpose()?;
+        if ledger.id.is_some() && ledger.id != id {
+            return Err(MicroledgerError::WrongLedger.into());
+        }
+        for (sn, signed_block) in ledger.blocks.iter().enumerate() {
+            let ledger_id = if sn == 0 { None } else { id.as_ref() };
+            check_ledger_id(ledger_id, &signed_block.block)?;
+        }
+        Ok(Self {
+            id,
+            blocks: ledger.blocks,
+            ..Self::default()
+        })
+    }
+}
+
+/// Checks if block belongs to microledger of given identifier, or is
+/// genesis block if there's none yet. Blocks created before ledger
+/// identifiers were introduced have no version string either, they are
+/// bound to the ledger by previous block digest only.
+fn check_ledger_id<I: Identifier + Serialize + Clone>(
+    id: Option<&SelfAddressingIdentifier>,
+    block: &Block<I>,
+) -> Result<()> {
+    match (id, &block.ledger_id) {
+        (None, None) => Ok(()),
+        (Some(id), Some(ledger_id)) if ledger_id == id => Ok(()),
+        (Some(_), None) if block.version.is_none() => Ok(()),
+        (Some(_), None) => Err(MicroledgerError::MissingLedgerId.into()),
+        _ => Err(MicroledgerError::WrongLedger.into()),
+    }
+}
+
+/// Checks if block carries sequence number. Only blocks created before
+/// versioning was introduced can omit it.
+fn check_numbered<I: Identifier + Serialize + Clone>(block: &Block<I>) -> Result<()> {
+    if block.version.is_some() && block.sequence_number.is_none() {
+        return Err(MicroledgerError::UnnumberedBlock.into());
+    }
+    Ok(())
+}
+
+/// Resolves signatures referring to signers by their positions in given
+/// controlling identifiers into ones naming them, so they can be verified.
+/// Signature referring to position out of range is an error.
+pub(crate) fn resolve_signatures<I, S>(signatures: &[S], controllers: &[I]) -> Result<Vec<S>>
+where
+    I: Identifier,
+    S: Signature<Identifier = I> + Clone,
+{
+    signatures
+        .iter()
+        .map(|signature| {
+            Ok(signature
+                .resolve(controllers)?
+                .unwrap_or_else(|| signature.clone()))
+        })
+        .collect()
+}
+
+fn default_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+impl<S, V, I> Default for MicroLedger<S, V, I>
+where
+    S: Serialize + Signature<Identifier = I>,
+    V: Verifier<Signature = S> + Default,
+    I: Identifier + Serialize + Clone,
+{
+    fn default() -> Self {
+        MicroLedger {
+            id: None,
+            blocks: vec![],
+            verifier: Arc::default(),
+            clock: default_clock(),
+            max_clock_skew: Duration::zero(),
+            policies: vec![],
+            limits: Limits::default(),
+            escrow: Escrow::default(),
+            witnesses: vec![],
+            witness_threshold: 0,
+        }
+    }
+}
+
+impl<S, V, I> fmt::Debug for MicroLedger<S, V, I>
+where
+    S: Serialize + Signature<Identifier = I> + fmt::Debug,
+    V: Verifier<Signature = S>,
+    I: Identifier + Serialize + Clone + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MicroLedger")
+            .field("id", &self.id)
+            .field("blocks", &self.blocks)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<S, V, I> MicroLedger<S, V, I>
@@ -43,62 +236,368 @@ where
 {
     pub fn new(verifier: Arc<V>) -> Self {
         MicroLedger {
+            id: None,
             blocks: vec![],
             verifier,
+            clock: default_clock(),
+            max_clock_skew: Duration::zero(),
+            policies: vec![],
+            limits: Limits::default(),
+            escrow: Escrow::default(),
+            witnesses: vec![],
+            witness_threshold: 0,
         }
     }
 
+    /// Sets clock used to timestamp new blocks and to validate timestamps of
+    /// anchored ones.
+    pub fn with_clock(self, clock: Arc<dyn Clock>) -> Self {
+        Self { clock, ..self }
+    }
+
+    /// Sets tolerance for differences between clocks of parties creating
+    /// blocks.
+    pub fn with_max_clock_skew(self, max_clock_skew: Duration) -> Self {
+        Self {
+            max_clock_skew,
+            ..self
+        }
+    }
+
+    /// Adds policy that every anchored block has to satisfy.
+    pub fn with_policy(mut self, policy: Arc<dyn BlockPolicy<I>>) -> Self {
+        self.policies.push(policy);
+        self
+    }
+
+    /// Sets bounds on size of anchored blocks.
+    pub fn with_limits(self, limits: Limits) -> Self {
+        Self { limits, ..self }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Sets how long blocks can wait in escrow for their predecessors or
+    /// signatures.
+    pub fn with_escrow_timeout(self, timeout: Duration) -> Self {
+        Self {
+            escrow: Escrow::new(timeout),
+            ..self
+        }
+    }
+
+    /// Returns blocks waiting in escrow.
+    pub fn escrow(&self) -> &Escrow<I, S> {
+        &self.escrow
+    }
+
+    /// Removes and returns escrowed blocks which waited longer than escrow
+    /// timeout.
+    pub fn prune_escrow(&mut self) -> Vec<EscrowedBlock<I, S>> {
+        self.escrow.prune(self.clock.now())
+    }
+
+    /// Sets identifiers of witnesses and number of them which need to
+    /// receipt a block for it to be considered witnessed.
+    pub fn with_witnesses(self, witnesses: Vec<I>, threshold: usize) -> Result<Self> {
+        if threshold > witnesses.len() {
+            return Err(ConfigError::ImproperThreshold.into());
+        }
+        Ok(Self {
+            witnesses,
+            witness_threshold: threshold,
+            ..self
+        })
+    }
+
+    pub fn witnesses(&self) -> &[I] {
+        &self.witnesses
+    }
+
+    /// Anchors block, or puts it in escrow if its predecessors or signatures
+    /// are missing. Escrowed blocks are anchored as soon as the gap is
+    /// filled, signatures of the same block received separately are merged.
+    /// Returns fingerprints of all blocks anchored in result.
+    pub fn receive(&mut self, block: SignedBlock<I, S>) -> Result<Vec<SelfAddressingIdentifier>> {
+        self.prune_escrow();
+        self.limits.check_block(&block.block)?;
+        // Blocks of unknown ledger can't be told apart before its genesis
+        // block arrives.
+        if self.id.is_some() {
+            check_ledger_id(self.id.as_ref(), &block.block)?;
+        }
+        check_numbered(&block.block)?;
+        self.check_validity(&block.block)?;
+        // Signatures are verified before the block is escrowed, so forged
+        // ones can't take place of valid signatures.
+        self.limits.check_attachments(&block.signatures)?;
+        self.check_received_signatures(&block)?;
+        let fingerprint = block.block.get_fingerprint()?;
+        self.escrow.insert(
+            block,
+            EscrowReason::MissingPrevious,
+            self.clock.now(),
+            &self.limits,
+        )?;
+
+        let mut anchored = vec![];
+        let mut result = Ok(());
+        loop {
+            let ready: Vec<_> = self
+                .escrow
+                .entries()
+                .iter()
+                .filter(|entry| !self.is_out_of_order(&entry.block.block))
+                .filter_map(|entry| entry.block.block.digital_fingerprint.clone())
+                .collect();
+            let mut progress = false;
+            for ready_fingerprint in ready {
+                let entry = match self.escrow.remove(&ready_fingerprint) {
+                    Some(entry) => entry,
+                    None => continue,
+                };
+                // Signatures which couldn't be verified before predecessors
+                // of the block arrived are left out if they aren't valid.
+                // Block whose signatures can't be resolved is dropped.
+                let signatures = match entry.block.verified_signatures(self) {
+                    Ok((signatures, _)) => signatures,
+                    Err(e) => {
+                        if ready_fingerprint == fingerprint {
+                            result = Err(e);
+                        }
+                        continue;
+                    }
+                };
+                let signed = SignedBlock::new(entry.block.block.clone(), signatures);
+                match self.anchor_block(signed.clone(), false) {
+                    Ok(()) => {
+                        anchored.push(ready_fingerprint);
+                        progress = true;
+                    }
+                    Err(Error::ConfigError(ConfigError::ThresholdNotMet)) => self.escrow.insert(
+                        signed,
+                        EscrowReason::MissingSignatures,
+                        entry.received,
+                        &self.limits,
+                    )?,
+                    // Invalid or outdated blocks are dropped from escrow.
+                    Err(e) if ready_fingerprint == fingerprint => result = Err(e),
+                    Err(_) => (),
+                }
+            }
+            if !progress {
+                break;
+            }
+        }
+        result.map(|_| anchored)
+    }
+
+    /// Verifies signatures of received block before it's escrowed, so that
+    /// forged signature can't take place of the valid one. Signatures
+    /// referring to signers by their positions can be resolved only if
+    /// predecessors of the block are known, otherwise they are verified
+    /// once the block is ready to be anchored.
+    fn check_received_signatures(&self, block: &SignedBlock<I, S>) -> Result<()> {
+        let resolved = if self.is_out_of_order(&block.block) {
+            block.signatures.clone()
+        } else {
+            resolve_signatures(
+                &block.signatures,
+                self.governing_controllers(self.blocks.len(), &block.block),
+            )?
+        };
+        let mut positions = vec![];
+        let mut signatures = vec![];
+        for (position, signature) in resolved.into_iter().enumerate() {
+            if !signature.get_signers().is_empty() {
+                positions.push(position);
+                signatures.push(signature);
+            }
+        }
+        let statuses = self.verifier.verify(&block.block.encode()?, signatures)?;
+        match first_failure(&statuses, positions.len()) {
+            Some((index, status)) => Err(MicroledgerError::InvalidSignature {
+                index: positions[index],
+                status,
+            }
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks if some of blocks preceding given block are missing.
+    fn is_out_of_order(&self, block: &Block<I>) -> bool {
+        match (block.sequence_number, &block.previous) {
+            (Some(sn), _) => sn > self.blocks.len() as u64,
+            (None, Some(previous)) => self.get_block_by_fingerprint(previous).is_err(),
+            (None, None) => false,
+        }
+    }
+
+    /// Returns identifier of microledger, which is fingerprint of its genesis
+    /// block.
+    pub fn id(&self) -> Option<&SelfAddressingIdentifier> {
+        self.id.as_ref()
+    }
+
     fn append_block(&mut self, signed_block: SignedBlock<I, S>) -> Result<()> {
+        if self.id.is_none() {
+            self.id = Some(signed_block.block.get_fingerprint()?);
+        }
         self.blocks.append(&mut vec![signed_block]);
         Ok(())
     }
 
+    /// Current time according to ledger clock.
+    pub(crate) fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Serialization kind of the next block: the one selected by ledger
+    /// configuration, or the one of the last block.
+    pub(crate) fn next_serialization_kind(&self) -> SerializationKind {
+        self.current_config()
+            .serialization_kind()
+            .unwrap_or_else(|| {
+                self.get_last_block()
+                    .map(Block::serialization_kind)
+                    .unwrap_or(SerializationKind::Json)
+            })
+    }
+
+    /// Sets sequence number and timestamp of block and binds it to this
+    /// microledger, unless it's genesis block.
+    fn bind_to_ledger(&self, block: Block<I>) -> Result<Block<I>> {
+        let block = block
+            .with_serialization_kind(self.next_serialization_kind())?
+            .with_sequence_number(self.blocks.len() as u64)?
+            .with_timestamp(self.clock.now())?;
+        match &self.id {
+            Some(id) => block.with_ledger_id(id.clone()),
+            None => Ok(block),
+        }
+    }
+
     pub fn pre_anchor_block(
         &self,
         controlling_identifiers: Vec<I>,
         seal_bundle: &SealBundle,
     ) -> Result<Block<I>> {
-        let prev = self.blocks.last().map(|sb| {
-            sb.block
-                .get_fingerprint()
-                .expect("Block without fingerprint")
-        });
+        let prev = self.last_fingerprint()?;
+        let seals = seal_bundle.get_fingerprints();
+        self.bind_to_ledger(Block::new(seals, prev, controlling_identifiers)?)
+    }
 
+    /// Returns block that closes the microledger. It is bound to the last
+    /// block and has to be signed by current controllers.
+    pub fn pre_terminate_block(&self, seal_bundle: &SealBundle) -> Result<Block<I>> {
+        let prev = self.last_fingerprint()?;
         let seals = seal_bundle.get_fingerprints();
-        Ok(Block::new(seals, prev, controlling_identifiers))
+        self.bind_to_ledger(Block::new_terminal(seals, prev)?)
     }
 
+    /// Anchors block submitted now. Its validity window is checked against
+    /// the ledger clock.
     pub fn anchor(&mut self, block: SignedBlock<I, S>) -> Result<()> {
-        let last = self.get_last_block();
-        let t = self.current_controlling_identifiers();
-        let controllers_check = match t {
-            // Provided signatures creators should match controlling identifiers
-            // designated in last block
-            Some(controllers) => block
-                .check_controlling_identifiers(&controllers)
-                .then_some(true)
-                .ok_or(MicroledgerError::WrongSigner)?,
-            // It's first block, check controlling identifeir from itself
-            None => block
-                .check_controlling_identifiers(&block.block.controlling_identifiers)
-                .then_some(true)
-                .ok_or(MicroledgerError::WrongSigner)?,
+        self.anchor_block(block, true)
+    }
+
+    /// Anchors block, checking its validity window only if it's submitted
+    /// now. Blocks loaded from exported ledger or waiting in escrow were
+    /// checked when they were submitted, and may be expired since then.
+    pub(crate) fn anchor_block(
+        &mut self,
+        block: SignedBlock<I, S>,
+        submitted_now: bool,
+    ) -> Result<()> {
+        self.limits.check_block(&block.block)?;
+        block.block.check_version()?;
+        self.limits.check_attachments(&block.signatures)?;
+        // Block is stored with submitted signatures, resolved ones are used
+        // for verification only.
+        let submitted = block.clone();
+        let controllers = self
+            .governing_controllers(self.blocks.len(), &block.block)
+            .to_vec();
+        let block = SignedBlock {
+            signatures: resolve_signatures(&block.signatures, &controllers)?,
+            ..block
         };
+        if let Some(terminal) = self.terminal_block() {
+            return Err(MicroledgerError::Terminated(terminal.block.get_fingerprint()?).into());
+        }
+        check_ledger_id(self.id.as_ref(), &block.block)?;
+        check_numbered(&block.block)?;
+        if let Some(sn) = block.block.sequence_number {
+            if sn != self.blocks.len() as u64 {
+                return Err(MicroledgerError::WrongSequenceNumber(sn).into());
+            }
+        }
+        self.check_timestamp(&block.block)?;
+        if submitted_now {
+            self.check_validity(&block.block)?;
+        }
+        let last = self.get_last_block();
+        // Provided signatures creators should match controlling identifiers
+        // designated in last block, or in the block itself if it's first one.
+        let controllers_check = block
+            .check_controlling_identifiers(&controllers)?
+            .then_some(true)
+            .ok_or(MicroledgerError::WrongSigner)?;
+        self.check_config(&block)?;
+        block.check_endorsers(&controllers)?;
+        if !block.verify_endorsements(self.verifier.clone())? {
+            return Err(MicroledgerError::WrongEndorsement.into());
+        }
+        self.check_receipts(&block)?;
+        for policy in &self.policies {
+            policy.check(&block.block).map_err(|e| match e {
+                Error::MicroError(MicroledgerError::PolicyViolation(_)) => e,
+                e => MicroledgerError::PolicyViolation(e.to_string()).into(),
+            })?;
+        }
         // Checks block binding and signatures.
-        if block.check_previous_block(last)?
-            && block.verify(self.verifier.clone())?
-            && controllers_check
-        {
-            self.append_block(block)
-        } else {
-            Err(MicroledgerError::WrongBlock.into())
+        if !block.check_previous_block(last)? || !controllers_check {
+            return Err(MicroledgerError::WrongBlock.into());
+        }
+        let statuses = block.verify_signatures(self.verifier.clone())?;
+        if let Some((index, status)) = first_failure(&statuses, block.signatures.len()) {
+            return Err(MicroledgerError::InvalidSignature { index, status }.into());
         }
+        let accepted = block
+            .signatures
+            .iter()
+            .chain(&block.endorsements)
+            .cloned()
+            .collect();
+        self.verifier.accept(&block.block.encode()?, accepted)?;
+        self.append_block(submitted)
     }
 
     pub fn get_last_block(&self) -> Option<&Block<I>> {
         self.blocks.last().map(|last| &last.block)
     }
 
+    /// Returns fingerprint of the last block, which the next block has to
+    /// point to.
+    pub fn last_fingerprint(&self) -> Result<Option<SelfAddressingIdentifier>> {
+        self.get_last_block()
+            .map(Block::get_fingerprint)
+            .transpose()
+    }
+
+    /// Returns true if microledger was closed by terminal block.
+    pub fn is_terminated(&self) -> bool {
+        self.terminal_block().is_some()
+    }
+
+    /// Returns block which terminated the microledger, if any.
+    pub fn terminal_block(&self) -> Option<&SignedBlock<I, S>> {
+        self.blocks.iter().find(|b| b.block.terminal)
+    }
+
     /// Returns copy of sub-microledger which last block matches the given fingerprint.
     pub fn at(&self, block_id: &SelfAddressingIdentifier) -> Option<Self> {
         let position = self
@@ -106,20 +605,305 @@ where
             .iter()
             .position(|b| block_id.eq(b.block.digital_fingerprint.as_ref().unwrap()));
         // .take_while(|b| !block_id.verify_binding(&Serialization::serialize(&b.block))).collect();
-        if let Some(position) = position {
-            let blocks: Vec<_> = self.blocks.clone().into_iter().take(position + 1).collect();
-            Some(Self {
-                blocks,
-                verifier: self.verifier.clone(),
-            })
+        position.and_then(|position| self.at_sn(position as u64))
+    }
+
+    /// Returns copy of sub-microledger which last block has the given
+    /// sequence number.
+    pub fn at_sn(&self, sn: u64) -> Option<Self> {
+        let blocks = self.blocks.get(..=sn as usize)?.to_vec();
+        Some(Self {
+            id: self.id.clone(),
+            blocks,
+            verifier: self.verifier.clone(),
+            clock: self.clock.clone(),
+            max_clock_skew: self.max_clock_skew,
+            policies: self.policies.clone(),
+            limits: self.limits.clone(),
+            escrow: Escrow::new(self.escrow.timeout()),
+            witnesses: self.witnesses.clone(),
+            witness_threshold: self.witness_threshold,
+        })
+    }
+
+    /// Returns copy of sub-microledger as it was at the given time, ie. up to
+    /// the last block timestamped not later than it.
+    pub fn at_time(&self, time: DateTime<Utc>) -> Result<Option<Self>> {
+        let mut position = None;
+        for (sn, signed_block) in self.blocks.iter().enumerate() {
+            match signed_block.block.get_timestamp()? {
+                Some(timestamp) if timestamp > time => break,
+                Some(_) => position = Some(sn),
+                None => (),
+            }
+        }
+        Ok(position.and_then(|sn| self.at_sn(sn as u64)))
+    }
+
+    /// Returns seals of blocks which validity window includes current time.
+    pub fn valid_seals(&self) -> Result<Vec<Seal>> {
+        self.valid_seals_at(self.clock.now())
+    }
+
+    /// Returns seals of blocks which validity window includes the given time.
+    pub fn valid_seals_at(&self, time: DateTime<Utc>) -> Result<Vec<Seal>> {
+        let mut seals = vec![];
+        for signed_block in &self.blocks {
+            if signed_block.block.is_valid_at(time)? {
+                seals.extend(signed_block.block.seals.iter().cloned());
+            }
+        }
+        Ok(seals)
+    }
+
+    /// Checks if block timestamp isn't earlier than timestamp of previous
+    /// blocks, nor later than current time, within allowed clock skew.
+    fn check_timestamp(&self, block: &Block<I>) -> Result<()> {
+        let timestamp = match block.get_timestamp()? {
+            Some(timestamp) => timestamp,
+            None => return Ok(()),
+        };
+        if timestamp > self.clock.now() + self.max_clock_skew {
+            return Err(MicroledgerError::TimestampInFuture.into());
+        }
+        for signed_block in self.blocks.iter().rev() {
+            if let Some(previous) = signed_block.block.get_timestamp()? {
+                if timestamp + self.max_clock_skew < previous {
+                    return Err(MicroledgerError::TimestampBeforePrevious.into());
+                }
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks if current time is within block validity window, with allowed
+    /// clock skew.
+    fn check_validity(&self, block: &Block<I>) -> Result<()> {
+        let now = self.clock.now();
+        let not_before = block.get_not_before()?;
+        let not_after = block.get_not_after()?;
+        if let (Some(not_before), Some(not_after)) = (not_before, not_after) {
+            if not_before > not_after {
+                return Err(BlockError::WrongValidityWindow.into());
+            }
+        }
+        if matches!(not_before, Some(not_before) if not_before > now + self.max_clock_skew) {
+            return Err(MicroledgerError::NotYetValid.into());
+        }
+        if matches!(not_after, Some(not_after) if not_after + self.max_clock_skew < now) {
+            return Err(MicroledgerError::Expired.into());
+        }
+        Ok(())
+    }
+
+    /// Configuration governing given block. Genesis block is governed by
+    /// itself.
+    fn governing_config(&self, block: &Block<I>) -> LedgerConfig {
+        if self.blocks.is_empty() {
+            block.config.clone().unwrap_or_default()
         } else {
-            None
+            self.current_config()
         }
     }
 
-    fn current_controlling_identifiers(&self) -> Option<Vec<I>> {
-        self.get_last_block()
-            .map(|block| block.controlling_identifiers.clone())
+    /// Controlling identifiers governing block at given position in the
+    /// ledger, ie. controllers of its predecessor. Genesis block is governed
+    /// by itself. Block which isn't anchored yet takes position following
+    /// the last block.
+    fn governing_controllers<'a>(&'a self, position: usize, block: &'a Block<I>) -> &'a [I] {
+        match position.checked_sub(1) {
+            Some(previous) => &self.blocks[previous].block.controlling_identifiers,
+            None => &block.controlling_identifiers,
+        }
+    }
+
+    /// Returns controlling identifiers entitled to sign given block and
+    /// number of them whose signatures are required to anchor it.
+    pub fn required_signers(&self, block: &Block<I>) -> (Vec<I>, usize) {
+        let config = self.governing_config(block);
+        let controllers = self
+            .governing_controllers(self.blocks.len(), block)
+            .to_vec();
+        let required_signatures = match &block.config {
+            // Amendment needs agreement of all current controllers.
+            Some(_) if !self.blocks.is_empty() => controllers.len(),
+            _ => config.threshold,
+        };
+        (controllers, required_signatures)
+    }
+
+    /// Checks if block obeys current ledger configuration. Genesis block is
+    /// checked against configuration it carries.
+    fn check_config(&self, block: &SignedBlock<I, S>) -> Result<()> {
+        let config = self.governing_config(&block.block);
+        config.check_block(&block.block)?;
+
+        let (controllers, required_signatures) = self.required_signers(&block.block);
+        if !block.block.terminal {
+            block
+                .block
+                .config
+                .as_ref()
+                .unwrap_or(&config)
+                .check_threshold(block.block.controlling_identifiers.len())?;
+        }
+        if block.count_signers(&controllers) < required_signatures {
+            return Err(ConfigError::ThresholdNotMet.into());
+        }
+        Ok(())
+    }
+
+    /// Adds endorsement to already anchored block of given fingerprint.
+    /// Endorsement is verified like block signatures, but its signers can't
+    /// be controlling identifiers that signed the block.
+    pub fn endorse(
+        &mut self,
+        fingerprint: &SelfAddressingIdentifier,
+        endorsement: S,
+    ) -> Result<()> {
+        let position = self
+            .blocks
+            .iter()
+            .position(|b| b.block.digital_fingerprint.as_ref() == Some(fingerprint))
+            .ok_or_else(|| MicroledgerError::MissingBlock(fingerprint.clone()))?;
+        let controllers = self.governing_controllers(position, &self.blocks[position].block);
+        let endorsed = self.blocks[position]
+            .clone()
+            .with_endorsements(vec![endorsement.clone()]);
+        endorsed.check_endorsers(controllers)?;
+        self.limits.check_attachments(&endorsed.endorsements)?;
+        let data = endorsed.block.encode()?;
+        let statuses = self.verifier.verify(&data, vec![endorsement.clone()])?;
+        if first_failure(&statuses, 1).is_some() {
+            return Err(MicroledgerError::WrongEndorsement.into());
+        }
+        self.verifier.accept(&data, vec![endorsement])?;
+        self.blocks[position] = endorsed;
+        Ok(())
+    }
+
+    /// Returns signatures of block of given fingerprint, with signatures
+    /// referring to signers by their positions resolved into ones naming
+    /// them, so they can be verified.
+    pub fn get_resolved_signatures(
+        &self,
+        fingerprint: &SelfAddressingIdentifier,
+    ) -> Result<Vec<S>> {
+        let position = self
+            .blocks
+            .iter()
+            .position(|b| b.block.digital_fingerprint.as_ref() == Some(fingerprint))
+            .ok_or_else(|| MicroledgerError::MissingBlock(fingerprint.clone()))?;
+        let block = &self.blocks[position];
+        resolve_signatures(
+            &block.signatures,
+            self.governing_controllers(position, &block.block),
+        )
+    }
+
+    /// Returns endorsements of block of given fingerprint.
+    pub fn get_endorsements(&self, fingerprint: &SelfAddressingIdentifier) -> Result<&[S]> {
+        self.get_block_by_fingerprint(fingerprint)
+            .map(|block| block.endorsements.as_slice())
+    }
+
+    /// Attaches witness receipt to already anchored block of given
+    /// fingerprint. Returns `false` if the witness already receipted the
+    /// block, in which case receipt is ignored.
+    pub fn add_receipt(
+        &mut self,
+        fingerprint: &SelfAddressingIdentifier,
+        receipt: Receipt<I, S>,
+    ) -> Result<bool> {
+        self.check_receipt(fingerprint, &receipt)?;
+        self.store_receipt(fingerprint, receipt)
+    }
+
+    /// Attaches receipt to anchored block without checking it. Used when
+    /// ledger is loaded without its witnesses, which can't be verified then.
+    /// Such receipt doesn't count towards witness threshold until it
+    /// verifies against witnesses set later.
+    pub(crate) fn store_receipt(
+        &mut self,
+        fingerprint: &SelfAddressingIdentifier,
+        receipt: Receipt<I, S>,
+    ) -> Result<bool> {
+        let block = self
+            .blocks
+            .iter_mut()
+            .find(|b| b.block.digital_fingerprint.as_ref() == Some(fingerprint))
+            .ok_or_else(|| MicroledgerError::MissingBlock(fingerprint.clone()))?;
+        if block.receipts.iter().any(|r| r.witness == receipt.witness) {
+            return Ok(false);
+        }
+        block.receipts.push(receipt);
+        Ok(true)
+    }
+
+    /// Returns witness receipts of block of given fingerprint.
+    pub fn get_receipts(&self, fingerprint: &SelfAddressingIdentifier) -> Result<&[Receipt<I, S>]> {
+        self.get_block_by_fingerprint(fingerprint)
+            .map(|block| block.receipts.as_slice())
+    }
+
+    /// Checks if enough witnesses receipted block of given fingerprint.
+    /// Receipts stored unverified count only if they are issued by ledger
+    /// witnesses and verify.
+    pub fn is_witnessed(&self, fingerprint: &SelfAddressingIdentifier) -> Result<bool> {
+        let mut witnessed = 0;
+        for receipt in self.get_receipts(fingerprint)? {
+            if self.witnesses.contains(&receipt.witness)
+                && receipt.verify(fingerprint, self.verifier.clone())?
+            {
+                witnessed += 1;
+            }
+        }
+        Ok(witnessed >= self.witness_threshold)
+    }
+
+    /// Checks if receipt is issued by one of ledger witnesses over given
+    /// block fingerprint.
+    fn check_receipt(
+        &self,
+        fingerprint: &SelfAddressingIdentifier,
+        receipt: &Receipt<I, S>,
+    ) -> Result<()> {
+        if !self.witnesses.contains(&receipt.witness) {
+            return Err(MicroledgerError::UnknownWitness.into());
+        }
+        if !receipt.verify(fingerprint, self.verifier.clone())? {
+            return Err(MicroledgerError::WrongReceipt.into());
+        }
+        Ok(())
+    }
+
+    /// Checks receipts attached to block before it's anchored. Every witness
+    /// can receipt the block once.
+    fn check_receipts(&self, block: &SignedBlock<I, S>) -> Result<()> {
+        if block.receipts.is_empty() {
+            return Ok(());
+        }
+        let fingerprint = block.block.get_fingerprint()?;
+        for (i, receipt) in block.receipts.iter().enumerate() {
+            if block.receipts[..i]
+                .iter()
+                .any(|r| r.witness == receipt.witness)
+            {
+                return Err(BlockError::DuplicatedSigner.into());
+            }
+            self.check_receipt(&fingerprint, receipt)?;
+        }
+        Ok(())
+    }
+
+    /// Returns configuration set in genesis block, including later amendments.
+    pub fn current_config(&self) -> LedgerConfig {
+        self.blocks
+            .iter()
+            .rev()
+            .find_map(|b| b.block.config.clone())
+            .unwrap_or_default()
     }
 
     /// Returns block of given fingerprint
@@ -142,6 +926,13 @@ where
             .ok_or_else(|| MicroledgerError::MissingBlock(fingerprint.clone()).into())
     }
 
+    /// Returns signed block of given sequence number
+    pub fn get_block_by_sn(&self, sn: u64) -> Result<&SignedBlock<I, S>> {
+        self.blocks
+            .get(sn as usize)
+            .ok_or_else(|| MicroledgerError::MissingSequenceNumber(sn).into())
+    }
+
     // pub fn get_seal_datums(&self, fingerprint: &DigitalFingerprint) -> Result<Vec<String>> {
     //     let block = self.get_block_by_fingerprint(fingerprint)?;
     //     let found_data: Result<Vec<_>> = block
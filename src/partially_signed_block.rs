@@ -0,0 +1,143 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    block::{Block, SignedBlock},
+    config::ConfigError,
+    microledger::{resolve_signatures, MicroLedger},
+    verifier::Verifier,
+    Encode, Identifier, Result, Signature,
+};
+
+/// Block collecting signatures of its controllers one by one. Once enough
+/// controllers signed it, it can be turned into `SignedBlock` and anchored.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PartiallySignedBlock<I, S>
+where
+    I: Identifier + Serialize + Clone,
+    S: Signature<Identifier = I>,
+{
+    pub block: Block<I>,
+    signatures: Vec<S>,
+}
+
+impl<I, S> PartiallySignedBlock<I, S>
+where
+    I: Identifier + Serialize + Clone + PartialEq,
+    S: Serialize + Clone + Signature<Identifier = I>,
+{
+    pub fn new(block: Block<I>) -> Self {
+        Self {
+            block,
+            signatures: vec![],
+        }
+    }
+
+    /// Adds signature to the block. Returns `false` if any of its signers
+    /// already signed the block, in which case signature is ignored.
+    pub fn add_signature(&mut self, signature: S) -> bool {
+        let signers = self.signers();
+        let duplicated = signature
+            .get_signers()
+            .iter()
+            .any(|signer| signers.contains(signer));
+        if !duplicated {
+            self.signatures.push(signature);
+        }
+        !duplicated
+    }
+
+    pub fn signatures(&self) -> &[S] {
+        &self.signatures
+    }
+
+    /// Returns identifiers which already signed the block.
+    pub fn signers(&self) -> Vec<I> {
+        self.signatures
+            .iter()
+            .flat_map(|signature| signature.get_signers())
+            .collect()
+    }
+
+    /// Returns signatures valid for the block, skipping ones of identifiers
+    /// which aren't controllers of given ledger or were already counted,
+    /// together with controllers who made them. Signatures referring to
+    /// signers by their positions are resolved for verification only, one
+    /// referring to position out of range is an error.
+    pub(crate) fn verified_signatures<V>(
+        &self,
+        ledger: &MicroLedger<S, V, I>,
+    ) -> Result<(Vec<S>, Vec<I>)>
+    where
+        V: Verifier<Signature = S>,
+    {
+        let (controllers, _) = ledger.required_signers(&self.block);
+        let resolved = resolve_signatures(&self.signatures, &controllers)?;
+        let statuses = ledger
+            .verifier
+            .verify(&self.block.encode()?, resolved.clone())?;
+        let (mut signatures, mut signers) = (vec![], vec![]);
+        for ((signature, resolved), status) in self.signatures.iter().zip(resolved).zip(statuses) {
+            let new_signers = resolved.get_signers();
+            if status.is_valid()
+                && !new_signers.is_empty()
+                && new_signers
+                    .iter()
+                    .all(|signer| controllers.contains(signer) && !signers.contains(signer))
+            {
+                signers.extend(new_signers);
+                signatures.push(signature.clone());
+            }
+        }
+        Ok((signatures, signers))
+    }
+
+    /// Returns controlling identifiers of given ledger which didn't sign the
+    /// block yet with valid signature.
+    pub fn missing_signers<V>(&self, ledger: &MicroLedger<S, V, I>) -> Result<Vec<I>>
+    where
+        V: Verifier<Signature = S>,
+    {
+        let (_, signers) = self.verified_signatures(ledger)?;
+        let (controllers, _) = ledger.required_signers(&self.block);
+        Ok(controllers
+            .into_iter()
+            .filter(|controller| !signers.contains(controller))
+            .collect())
+    }
+
+    /// Returns number of valid signatures still needed to meet the threshold
+    /// of given ledger.
+    pub fn remaining_signatures<V>(&self, ledger: &MicroLedger<S, V, I>) -> Result<usize>
+    where
+        V: Verifier<Signature = S>,
+    {
+        let (_, signers) = self.verified_signatures(ledger)?;
+        let (_, required) = ledger.required_signers(&self.block);
+        Ok(required.saturating_sub(signers.len()))
+    }
+
+    /// Checks if enough controllers validly signed the block to anchor it in
+    /// given ledger.
+    pub fn is_complete<V>(&self, ledger: &MicroLedger<S, V, I>) -> Result<bool>
+    where
+        V: Verifier<Signature = S>,
+    {
+        Ok(self.remaining_signatures(ledger)? == 0)
+    }
+
+    /// Converts block into `SignedBlock`, if enough controllers validly
+    /// signed it to anchor it in given ledger. Signatures which aren't valid
+    /// are left out.
+    pub fn to_signed_block<V>(self, ledger: &MicroLedger<S, V, I>) -> Result<SignedBlock<I, S>>
+    where
+        V: Verifier<Signature = S>,
+    {
+        let (signatures, signers) = self.verified_signatures(ledger)?;
+        let (_, required) = ledger.required_signers(&self.block);
+        if signers.len() >= required {
+            Ok(self.block.to_signed_block(signatures))
+        } else {
+            Err(ConfigError::ThresholdNotMet.into())
+        }
+    }
+}
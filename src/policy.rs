@@ -0,0 +1,20 @@
+use serde::Serialize;
+
+use crate::{block::Block, Identifier, Result};
+
+/// Hook checking blocks before they are anchored in microledger, ie. whether
+/// block metadata follows expected schema. Block is rejected if any of ledger
+/// policies returns an error, which is reported as policy violation.
+pub trait BlockPolicy<I: Identifier + Serialize + Clone>: Send + Sync {
+    fn check(&self, block: &Block<I>) -> Result<()>;
+}
+
+impl<I, F> BlockPolicy<I> for F
+where
+    I: Identifier + Serialize + Clone,
+    F: Fn(&Block<I>) -> Result<()> + Send + Sync,
+{
+    fn check(&self, block: &Block<I>) -> Result<()> {
+        self(block)
+    }
+}
@@ -0,0 +1,68 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use thiserror::Error;
+
+use crate::Result;
+
+#[derive(Error, Debug)]
+pub enum PrimitiveError {
+    #[error("Unexpected derivation code of {0}")]
+    WrongCode(String),
+    #[error("Improper encoding of {0}")]
+    WrongEncoding(String),
+    #[error("Improper public key")]
+    WrongPublicKey,
+    #[error("Improper signature")]
+    WrongSignature,
+}
+
+/// Number of zero bytes prepended to primitive of given size, so it's
+/// encoded on whole base64 characters.
+fn lead_size(size: usize) -> usize {
+    (3 - size % 3) % 3
+}
+
+/// Encodes raw bytes of public key or signature as CESR primitive of given
+/// derivation code. Code replaces base64 characters of prepended zero
+/// bytes.
+pub(crate) fn encode(code: &str, raw: &[u8]) -> String {
+    let lead = lead_size(raw.len());
+    let mut padded = vec![0; lead];
+    padded.extend_from_slice(raw);
+    let encoded = URL_SAFE_NO_PAD.encode(padded);
+    format!("{}{}", code, &encoded[lead..])
+}
+
+/// Decodes raw bytes of CESR primitive of given derivation code and size.
+pub(crate) fn decode(code: &str, size: usize, encoded: &str) -> Result<Vec<u8>> {
+    let data = encoded
+        .strip_prefix(code)
+        .ok_or_else(|| PrimitiveError::WrongCode(encoded.to_string()))?;
+    let lead = lead_size(size);
+    let padded = URL_SAFE_NO_PAD
+        .decode(format!("{}{}", "A".repeat(lead), data))
+        .map_err(|_e| PrimitiveError::WrongEncoding(encoded.to_string()))?;
+    if padded.len() != lead + size || padded[..lead].iter().any(|b| *b != 0) {
+        return Err(PrimitiveError::WrongEncoding(encoded.to_string()).into());
+    }
+    Ok(padded[lead..].to_vec())
+}
+
+#[test]
+fn test_primitive_encoding() -> Result<()> {
+    // Codes of one and two characters replace padding
+    assert_eq!(encode("D", &[0; 32]).len(), 44);
+    assert_eq!(encode("0B", &[0; 64]).len(), 88);
+    // and four character codes are prepended to primitives without padding
+    let encoded = encode("1AAB", &[2; 33]);
+    assert_eq!(encoded.len(), 48);
+    assert_eq!(decode("1AAB", 33, &encoded)?, vec![2; 33]);
+
+    assert!(matches!(
+        decode("1AAJ", 33, &encoded),
+        Err(crate::error::Error::PrimitiveError(
+            PrimitiveError::WrongCode(_)
+        ))
+    ));
+    assert!(decode("1AAB", 32, &encoded).is_err());
+    Ok(())
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use said::SelfAddressingIdentifier;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    verifier::{first_failure, Verifier},
+    Identifier, Result, Signature,
+};
+
+/// Acknowledgement of a witness that it has seen a block. Witness signs
+/// fingerprint of the block, so receipts can be collected after the block
+/// is anchored.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Receipt<I, S>
+where
+    I: Identifier,
+    S: Signature<Identifier = I>,
+{
+    #[serde(rename = "w")]
+    pub witness: I,
+    #[serde(rename = "s")]
+    pub signature: S,
+}
+
+impl<I, S> Receipt<I, S>
+where
+    I: Identifier + PartialEq,
+    S: Clone + Signature<Identifier = I>,
+{
+    pub fn new(witness: I, signature: S) -> Self {
+        Self { witness, signature }
+    }
+
+    /// Checks if receipt is signed by its witness over given block
+    /// fingerprint.
+    pub fn verify<V: Verifier<Signature = S>>(
+        &self,
+        fingerprint: &SelfAddressingIdentifier,
+        verifier: Arc<V>,
+    ) -> Result<bool> {
+        if !self.signature.get_signers().contains(&self.witness) {
+            return Ok(false);
+        }
+        let statuses = verifier.verify(
+            fingerprint.to_string().as_bytes(),
+            vec![self.signature.clone()],
+        )?;
+        Ok(first_failure(&statuses, 1).is_none())
+    }
+}
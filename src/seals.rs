@@ -16,11 +16,21 @@ impl Seal {
             Seal::Attached(sai) => sai.to_string(),
         }
     }
-    pub fn to_str(&self) -> String {
+    /// Returns digest of sealed data.
+    pub fn digest(&self) -> &SelfAddressingIdentifier {
+        match self {
+            Seal::Attached(sai) => sai,
+        }
+    }
+    /// Returns code of seal type, ie. `A` for attached seal.
+    pub fn seal_type(&self) -> &'static str {
         match self {
-            Seal::Attached(sai) => ["A", &sai.to_string()].join(""),
+            Seal::Attached(_) => "A",
         }
     }
+    pub fn to_str(&self) -> String {
+        [self.seal_type(), &self.fingerprint()].join("")
+    }
 }
 
 impl FromStr for Seal {
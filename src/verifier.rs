@@ -1,6 +1,77 @@
+use std::fmt;
+
 use crate::Result;
+
+/// Outcome of verification of a single signature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureStatus {
+    Valid,
+    /// Signature doesn't match signed data.
+    Invalid,
+    /// Key of the signer is unknown to the verifier.
+    UnknownKey,
+    /// Verifier can't handle this type of signature.
+    UnsupportedType,
+}
+
+impl SignatureStatus {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, SignatureStatus::Valid)
+    }
+}
+
+impl fmt::Display for SignatureStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            SignatureStatus::Valid => "valid",
+            SignatureStatus::Invalid => "invalid",
+            SignatureStatus::UnknownKey => "unknown key",
+            SignatureStatus::UnsupportedType => "unsupported type",
+        };
+        f.write_str(description)
+    }
+}
+
 pub trait Verifier {
     type Signature;
 
-    fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<bool>;
+    /// Verifies every signature of given data separately. Returns their
+    /// statuses in the order of signatures.
+    fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>>;
+
+    /// Called when signatures of given data were accepted, ie. signed block
+    /// is being anchored. Verifiers of keys which can change record here
+    /// keys which verified the signatures, to verify them later. Does
+    /// nothing by default.
+    fn accept(&self, _data: &[u8], _s: Vec<Self::Signature>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns position and status of the first of `count` signatures which
+/// isn't valid. Signatures without status aren't valid.
+pub fn first_failure(
+    statuses: &[SignatureStatus],
+    count: usize,
+) -> Option<(usize, SignatureStatus)> {
+    (0..count)
+        .map(|i| {
+            (
+                i,
+                statuses.get(i).copied().unwrap_or(SignatureStatus::Invalid),
+            )
+        })
+        .find(|(_, status)| !status.is_valid())
+}
+
+#[test]
+fn test_first_failure() {
+    use SignatureStatus::*;
+    assert_eq!(first_failure(&[Valid, Valid], 2), None);
+    assert_eq!(
+        first_failure(&[Valid, UnknownKey, Invalid], 3),
+        Some((1, UnknownKey))
+    );
+    // Verifier didn't report status of every signature
+    assert_eq!(first_failure(&[Valid], 2), Some((1, Invalid)));
 }
@@ -0,0 +1,255 @@
+use std::{fmt, str::FromStr};
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::{canonical, error::Error, Result};
+
+/// Protocol code put in version strings of microledger blocks.
+pub const PROTOCOL: &str = "MICR";
+/// Version of block format produced by this implementation.
+pub const CURRENT_VERSION: (u8, u8) = (1, 0);
+/// Largest block size which fits in six hex digits of version string.
+pub const MAX_SIZE: usize = 0xff_ffff;
+
+#[derive(Error, Debug)]
+pub enum VersionError {
+    #[error("Improper version string: {0}")]
+    WrongVersionString(String),
+    #[error("Unknown protocol: {0}")]
+    UnknownProtocol(String),
+    #[error("Unsupported block version: {0}.{1}")]
+    UnsupportedVersion(u8, u8),
+    #[error("Unsupported serialization kind: {0}")]
+    UnsupportedKind(String),
+    #[error("Block declares {declared} serialization, but is serialized as {actual}")]
+    WrongKind { declared: String, actual: String },
+    #[error("Block isn't serialized canonically")]
+    NotCanonical,
+    #[error("Declared block size {declared} doesn't match actual size {actual}")]
+    WrongSize { declared: usize, actual: usize },
+    #[error("Block size {0} exceeds maximum size expressible in version string")]
+    TooLarge(usize),
+}
+
+/// Serialization kind designated in version string.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SerializationKind {
+    Json,
+    /// Canonical JSON, as defined in RFC 8785.
+    Jcs,
+    Cbor,
+    Mgpk,
+}
+
+impl SerializationKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SerializationKind::Json => "JSON",
+            SerializationKind::Jcs => "JCSN",
+            SerializationKind::Cbor => "CBOR",
+            SerializationKind::Mgpk => "MGPK",
+        }
+    }
+
+    /// Checks if data recognized as serialized in `actual` kind can be of
+    /// this kind. Canonical JSON is recognized as JSON.
+    pub fn is_compatible(&self, actual: SerializationKind) -> bool {
+        *self == actual || (*self == SerializationKind::Jcs && actual == SerializationKind::Json)
+    }
+
+    /// Recognizes serialization kind by the first byte of serialized map:
+    /// `{` for JSON (canonical or not), major type 5 for CBOR and fixmap or map type for
+    /// MessagePack.
+    pub fn sniff(data: &[u8]) -> Result<Self> {
+        match data.first() {
+            Some(b'{') => Ok(SerializationKind::Json),
+            Some(0xa0..=0xbf) => Ok(SerializationKind::Cbor),
+            Some(0x80..=0x8f) | Some(0xde) | Some(0xdf) => Ok(SerializationKind::Mgpk),
+            _ => Err(VersionError::UnsupportedKind("unknown".to_string()).into()),
+        }
+    }
+
+    pub fn encode<T: Serialize>(&self, value: &T) -> Result<Vec<u8>> {
+        Ok(match self {
+            SerializationKind::Json => serde_json::to_vec(value)?,
+            SerializationKind::Jcs => canonical::to_vec(value)?,
+            SerializationKind::Cbor => serde_cbor::to_vec(value)?,
+            SerializationKind::Mgpk => rmp_serde::to_vec_named(value)?,
+        })
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, data: &[u8]) -> Result<T> {
+        Ok(match self {
+            SerializationKind::Json | SerializationKind::Jcs => serde_json::from_slice(data)?,
+            SerializationKind::Cbor => serde_cbor::from_slice(data)?,
+            SerializationKind::Mgpk => rmp_serde::from_slice(data)?,
+        })
+    }
+}
+
+impl FromStr for SerializationKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "JSON" => Ok(SerializationKind::Json),
+            "JCSN" => Ok(SerializationKind::Jcs),
+            "CBOR" => Ok(SerializationKind::Cbor),
+            "MGPK" => Ok(SerializationKind::Mgpk),
+            _ => Err(VersionError::UnsupportedKind(s.to_string()).into()),
+        }
+    }
+}
+
+/// KERI-style version string of a block, ie. `MICR10JSON0000fd_`. It consists
+/// of protocol code, major and minor version as hex digits, serialization kind
+/// and size of serialized block as 6 hex digits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+    pub kind: SerializationKind,
+    pub size: usize,
+}
+
+impl Version {
+    pub fn new(kind: SerializationKind, size: usize) -> Self {
+        let (major, minor) = CURRENT_VERSION;
+        Self {
+            major,
+            minor,
+            kind,
+            size,
+        }
+    }
+
+    /// Sets declared size of serialized block. Sizes which don't fit in
+    /// version string are rejected.
+    pub fn with_size(self, size: usize) -> Result<Self> {
+        if size > MAX_SIZE {
+            return Err(VersionError::TooLarge(size).into());
+        }
+        Ok(Self { size, ..self })
+    }
+
+    /// Checks if version is supported and matches actual serialized block size.
+    pub fn check(&self, actual_size: usize) -> Result<()> {
+        if (self.major, self.minor) > CURRENT_VERSION {
+            return Err(VersionError::UnsupportedVersion(self.major, self.minor).into());
+        }
+        if self.size != actual_size {
+            return Err(VersionError::WrongSize {
+                declared: self.size,
+                actual: actual_size,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{:x}{:x}{}{:06x}_",
+            PROTOCOL,
+            self.major,
+            self.minor,
+            self.kind.code(),
+            self.size
+        )
+    }
+}
+
+impl FromStr for Version {
+    type Err = Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let wrong_version = || VersionError::WrongVersionString(s.to_string());
+        if s.len() != 17 || !s.is_ascii() || !s.ends_with('_') {
+            return Err(wrong_version().into());
+        }
+        if &s[..4] != PROTOCOL {
+            return Err(VersionError::UnknownProtocol(s[..4].to_string()).into());
+        }
+        let major = u8::from_str_radix(&s[4..5], 16).map_err(|_e| wrong_version())?;
+        let minor = u8::from_str_radix(&s[5..6], 16).map_err(|_e| wrong_version())?;
+        let kind = s[6..10].parse()?;
+        let size = usize::from_str_radix(&s[10..16], 16).map_err(|_e| wrong_version())?;
+        Ok(Self {
+            major,
+            minor,
+            kind,
+            size,
+        })
+    }
+}
+
+impl Serialize for Version {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Version {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Version, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Version::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[test]
+pub fn test_version_string() {
+    let version: Version = "MICR10JSON0000fd_".parse().unwrap();
+    assert_eq!(version, Version::new(SerializationKind::Json, 253));
+    assert_eq!(version.to_string(), "MICR10JSON0000fd_");
+
+    assert!("KERI10JSON0000fd_".parse::<Version>().is_err());
+    assert!("MICR10XXXX0000fd_".parse::<Version>().is_err());
+    assert!("MICR10JSON0000fd".parse::<Version>().is_err());
+    assert_eq!(
+        "MICR10CBOR0000fd_".parse::<Version>().unwrap().kind,
+        SerializationKind::Cbor
+    );
+    assert!(version.check(253).is_ok());
+    assert!(version.check(254).is_err());
+    assert!(Version {
+        major: 2,
+        ..version
+    }
+    .check(253)
+    .is_err());
+
+    let largest = version.clone().with_size(MAX_SIZE).unwrap();
+    assert_eq!(largest.to_string(), "MICR10JSONffffff_");
+    assert_eq!(largest.to_string().parse::<Version>().unwrap(), largest);
+    assert!(matches!(
+        version.with_size(MAX_SIZE + 1),
+        Err(Error::VersionError(VersionError::TooLarge(_)))
+    ));
+}
+
+#[test]
+pub fn test_sniff_serialization_kind() {
+    let value = serde_json::json!({"v": "MICR10JSON0000fd_"});
+    for kind in [
+        SerializationKind::Json,
+        SerializationKind::Jcs,
+        SerializationKind::Cbor,
+        SerializationKind::Mgpk,
+    ] {
+        let encoded = kind.encode(&value).unwrap();
+        assert!(kind.is_compatible(SerializationKind::sniff(&encoded).unwrap()));
+        let decoded: serde_json::Value = kind.decode(&encoded).unwrap();
+        assert_eq!(decoded, value);
+    }
+    assert!(SerializationKind::sniff(b"").is_err());
+}
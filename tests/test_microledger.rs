@@ -1,9 +1,20 @@
 pub(crate) mod helpers {
+    use std::sync::{Arc, Mutex};
+
     use base64::{engine::general_purpose, Engine};
-    use ed25519_dalek::{PublicKey, Signature as EdLibSignature, Verifier as EdLibVerifier};
+    use chrono::{DateTime, Duration, Utc};
+    use ed25519_dalek::{
+        Keypair, PublicKey, Signature as EdLibSignature, Signer, Verifier as EdLibVerifier,
+    };
+    use rand::rngs::OsRng;
     use serde::{Deserialize, Serialize};
 
-    use microledger::{verifier::Verifier, Identifier, Result, Signature};
+    use microledger::{
+        block::{Block, BlockError},
+        clock::Clock,
+        verifier::{SignatureStatus, Verifier},
+        Encode, Identifier, Result, Signature,
+    };
 
     #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
     pub struct EasyIdentifier(pub String);
@@ -15,8 +26,8 @@ pub(crate) mod helpers {
     impl Signature for EdSignature {
         type Identifier = EasyIdentifier;
 
-        fn get_signer(&self) -> Option<Self::Identifier> {
-            Some(EasyIdentifier("Identifier1".into()))
+        fn get_signers(&self) -> Vec<Self::Identifier> {
+            vec![EasyIdentifier("Identifier1".into())]
         }
     }
     pub struct EdVerifier(pub PublicKey);
@@ -24,35 +35,264 @@ pub(crate) mod helpers {
     impl Verifier for EdVerifier {
         type Signature = EdSignature;
 
-        fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<bool> {
-            Ok(s.iter().all(|sig| {
-                let raw_sig = general_purpose::STANDARD_NO_PAD.decode(&sig.0).unwrap();
-                self.0
-                    .verify(data, &EdLibSignature::from_bytes(&raw_sig).unwrap())
-                    .is_ok()
-            }))
+        fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>> {
+            Ok(s.iter()
+                .map(|sig| verify_ed25519(&self.0, data, &sig.0))
+                .collect())
+        }
+    }
+
+    /// Generates keypair of single ledger controller. Returns verifier of its
+    /// signatures, signing function and the controller identifier.
+    pub fn ed25519_fixture() -> (
+        Arc<EdVerifier>,
+        impl Fn(Vec<u8>) -> EdSignature,
+        EasyIdentifier,
+    ) {
+        let kp = Keypair::generate(&mut OsRng {});
+        let validator = Arc::new(EdVerifier(kp.public));
+        let sign = move |data: Vec<u8>| {
+            EdSignature(general_purpose::STANDARD_NO_PAD.encode(kp.sign(&data).as_ref()))
+        };
+        (validator, sign, EasyIdentifier("Identifier1".to_string()))
+    }
+
+    /// Parties of a ledger with multiple controllers, ie. its controllers,
+    /// endorsers or witnesses, each with its own keypair.
+    pub struct Signers {
+        pub identifiers: Vec<EasyIdentifier>,
+        pub keypairs: Vec<Keypair>,
+    }
+
+    impl Signers {
+        /// Returns verifier which knows keys of all the parties.
+        pub fn verifier(&self) -> MultiVerifier {
+            MultiVerifier(
+                self.identifiers
+                    .iter()
+                    .cloned()
+                    .zip(self.keypairs.iter().map(|kp| kp.public))
+                    .collect(),
+            )
+        }
+
+        /// Signs data by party of given position.
+        pub fn sign_data(&self, i: usize, data: &[u8]) -> AttributedSignature {
+            AttributedSignature::new(&self.identifiers[i], &self.keypairs[i], data)
+        }
+
+        pub fn sign(&self, i: usize, block: &Block<EasyIdentifier>) -> Result<AttributedSignature> {
+            Ok(self.sign_data(i, &block.encode()?))
+        }
+
+        /// Signs block by party of given position, referring to it by the
+        /// same position in controlling identifiers.
+        pub fn sign_indexed(
+            &self,
+            i: usize,
+            block: &Block<EasyIdentifier>,
+        ) -> Result<CompactSignature> {
+            Ok(CompactSignature::indexed(
+                i,
+                &self.keypairs[i],
+                &block.encode()?,
+            ))
+        }
+    }
+
+    /// Generates keypairs of parties of given names.
+    pub fn multi_controller_fixture(names: &[&str]) -> Signers {
+        Signers {
+            identifiers: names
+                .iter()
+                .map(|name| EasyIdentifier(name.to_string()))
+                .collect(),
+            keypairs: names
+                .iter()
+                .map(|_| Keypair::generate(&mut OsRng {}))
+                .collect(),
+        }
+    }
+
+    fn verify_ed25519(key: &PublicKey, data: &[u8], signature: &str) -> SignatureStatus {
+        let signature = general_purpose::STANDARD_NO_PAD
+            .decode(signature)
+            .ok()
+            .and_then(|raw_sig| EdLibSignature::from_bytes(&raw_sig).ok());
+        match signature {
+            Some(signature) if key.verify(data, &signature).is_ok() => SignatureStatus::Valid,
+            _ => SignatureStatus::Invalid,
+        }
+    }
+
+    /// Signatures which name their signers, for ledgers with multiple
+    /// controllers.
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct AttributedSignature(pub Vec<(EasyIdentifier, String)>);
+
+    impl AttributedSignature {
+        pub fn new(signer: &EasyIdentifier, keypair: &Keypair, data: &[u8]) -> Self {
+            Self(vec![(
+                signer.clone(),
+                general_purpose::STANDARD_NO_PAD.encode(keypair.sign(data).as_ref()),
+            )])
+        }
+
+        /// Groups signatures into one signature value.
+        pub fn join(mut self, other: Self) -> Self {
+            self.0.extend(other.0);
+            self
+        }
+    }
+
+    impl Signature for AttributedSignature {
+        type Identifier = EasyIdentifier;
+
+        fn get_signers(&self) -> Vec<Self::Identifier> {
+            self.0.iter().map(|(signer, _)| signer.clone()).collect()
+        }
+    }
+
+    /// Verifies signatures with public keys of their signers.
+    pub struct MultiVerifier(pub Vec<(EasyIdentifier, PublicKey)>);
+
+    impl Verifier for MultiVerifier {
+        type Signature = AttributedSignature;
+
+        /// Signature grouping several signatures is valid if all of them are.
+        fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>> {
+            Ok(s.iter()
+                .map(|sig| {
+                    sig.0
+                        .iter()
+                        .map(
+                            |(signer, sig)| match self.0.iter().find(|(id, _)| id == signer) {
+                                Some((_, key)) => verify_ed25519(key, data, sig),
+                                None => SignatureStatus::UnknownKey,
+                            },
+                        )
+                        .find(|status| !status.is_valid())
+                        .unwrap_or(SignatureStatus::Valid)
+                })
+                .collect())
+        }
+    }
+
+    /// Signatures which name their signers or refer to them by positions in
+    /// controlling identifiers of the ledger.
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub enum CompactSignature {
+        Attributed(AttributedSignature),
+        Indexed(Vec<(usize, String)>),
+    }
+
+    impl CompactSignature {
+        pub fn indexed(index: usize, keypair: &Keypair, data: &[u8]) -> Self {
+            Self::Indexed(vec![(
+                index,
+                general_purpose::STANDARD_NO_PAD.encode(keypair.sign(data).as_ref()),
+            )])
+        }
+    }
+
+    impl Signature for CompactSignature {
+        type Identifier = EasyIdentifier;
+
+        fn get_signers(&self) -> Vec<Self::Identifier> {
+            match self {
+                CompactSignature::Attributed(signature) => signature.get_signers(),
+                CompactSignature::Indexed(_) => vec![],
+            }
+        }
+
+        fn resolve(&self, controlling_identifiers: &[EasyIdentifier]) -> Result<Option<Self>> {
+            match self {
+                CompactSignature::Attributed(_) => Ok(None),
+                CompactSignature::Indexed(signatures) => {
+                    let signatures = signatures
+                        .iter()
+                        .map(|(index, signature)| {
+                            controlling_identifiers
+                                .get(*index)
+                                .map(|signer| (signer.clone(), signature.clone()))
+                                .ok_or_else(|| BlockError::WrongSignatureIndex(*index).into())
+                        })
+                        .collect::<Result<_>>()?;
+                    Ok(Some(CompactSignature::Attributed(AttributedSignature(
+                        signatures,
+                    ))))
+                }
+            }
+        }
+    }
+
+    /// Verifies compact signatures once they are resolved.
+    pub struct CompactVerifier(pub MultiVerifier);
+
+    impl Verifier for CompactVerifier {
+        type Signature = CompactSignature;
+
+        fn verify(&self, data: &[u8], s: Vec<Self::Signature>) -> Result<Vec<SignatureStatus>> {
+            s.into_iter()
+                .map(|signature| match signature {
+                    CompactSignature::Attributed(signature) => {
+                        Ok(self.0.verify(data, vec![signature])?[0])
+                    }
+                    CompactSignature::Indexed(_) => Ok(SignatureStatus::UnsupportedType),
+                })
+                .collect()
+        }
+    }
+
+    pub struct TestClock(pub Mutex<DateTime<Utc>>);
+
+    impl TestClock {
+        pub fn advance(&self, duration: Duration) {
+            let mut now = self.0.lock().unwrap();
+            *now += duration;
+        }
+    }
+
+    impl Clock for TestClock {
+        fn now(&self) -> DateTime<Utc> {
+            *self.0.lock().unwrap()
         }
     }
 }
 #[cfg(test)]
 pub mod test {
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
     use base64::{engine::general_purpose, Engine};
+    use chrono::{Duration, Utc};
     use ed25519_dalek::Signer;
     use rand::rngs::OsRng;
     use said::derivation::{HashFunction, HashFunctionCode};
+    use said::SelfAddressingIdentifier;
 
     use microledger::{
-        block::Block,
+        block::{Block, BlockError, SignedBlock},
+        block_builder::BlockBuilder,
+        canonical,
+        clock::Clock,
+        config::{ConfigError, LedgerConfig},
         error::Error,
+        escrow::EscrowReason,
+        limits::{LimitError, Limits},
         microledger::{MicroLedger, MicroledgerError},
+        partially_signed_block::PartiallySignedBlock,
+        receipt::Receipt,
         seal_bundle::{SealBundle, SealData},
         seals::Seal,
-        Encode, Result,
+        verifier::SignatureStatus,
+        version::{SerializationKind, VersionError},
+        Encode, Result, Signature,
     };
 
-    use crate::helpers::{EasyIdentifier, EdSignature, EdVerifier};
+    use crate::helpers::{
+        ed25519_fixture, multi_controller_fixture, AttributedSignature, CompactSignature,
+        CompactVerifier, EasyIdentifier, EdSignature, EdVerifier, MultiVerifier, TestClock,
+    };
 
     #[test]
     fn test_block_serialization() -> Result<()> {
@@ -62,7 +302,7 @@ pub mod test {
         let seal = HashFunction::from(HashFunctionCode::Blake3_256).derive("exmaple".as_bytes());
         let prev =
             Some(HashFunction::from(HashFunctionCode::Blake3_256).derive("exmaple".as_bytes()));
-        let block = Block::new(vec![Seal::Attached(seal)], prev, vec![(id)]);
+        let block = Block::new(vec![Seal::Attached(seal)], prev, vec![(id)])?;
         println!("{}", String::from_utf8(block.encode()?).unwrap());
 
         let deserialized_block: Block<EasyIdentifier> =
@@ -71,6 +311,982 @@ pub mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_block_versions() -> Result<()> {
+        // Block created before versioning was introduced
+        let legacy = r#"{"ci":["Identifier1"],"d":"EL-nsW-tAdE0ex7Wm9B-V3J-ilrWOXiAt-r-8713pOHO","p":"EASZwg_zixnbrxNogPpgYXh9nauPBqG4jiNVn3cYFTT_","s":["AEOqPFj2zhoKSXkSRxeWNS7NQbvjBTreKhukIxWJKZyAP"]}"#;
+        let block = Block::<EasyIdentifier>::from_slice(legacy.as_bytes())?;
+        assert!(block.version.is_none());
+        assert_eq!(block.encode()?, legacy.as_bytes());
+
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = Block::new(
+            seals.get_fingerprints(),
+            None,
+            vec![EasyIdentifier("Identifier1".to_string())],
+        )?;
+        let encoded = String::from_utf8(block.encode()?).unwrap();
+        assert!(encoded.starts_with(r#"{"v":"MICR10JSON"#));
+        assert_eq!(
+            block.version.as_ref().map(|version| version.size),
+            Some(encoded.len())
+        );
+        assert_eq!(
+            Block::<EasyIdentifier>::from_slice(encoded.as_bytes())?,
+            block
+        );
+
+        // Declared size doesn't match
+        let mut too_long = encoded.clone();
+        too_long.insert(1, ' ');
+        assert!(matches!(
+            Block::<EasyIdentifier>::from_slice(too_long.as_bytes()),
+            Err(Error::VersionError(VersionError::WrongSize { .. }))
+        ));
+
+        // Unknown future version
+        let future = encoded.replace("MICR10JSON", "MICR20JSON");
+        assert!(matches!(
+            Block::<EasyIdentifier>::from_slice(future.as_bytes()),
+            Err(Error::VersionError(VersionError::UnsupportedVersion(2, 0)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialization_kinds() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+
+        let mut microledger = MicroLedger::new(validator);
+        for kind in [
+            SerializationKind::Json,
+            SerializationKind::Cbor,
+            SerializationKind::Mgpk,
+        ] {
+            let block = microledger
+                .pre_anchor_block(vec![identifier.clone()], &seals)?
+                .with_serialization_kind(kind)?;
+            let encoded = block.encode()?;
+            assert_eq!(SerializationKind::sniff(&encoded)?, kind);
+            assert_eq!(
+                block.version.as_ref().map(|version| version.size),
+                Some(encoded.len())
+            );
+            assert_eq!(Block::<EasyIdentifier>::from_slice(&encoded)?, block);
+
+            let signature = sign(encoded);
+            microledger.anchor(block.to_signed_block(vec![signature]))?;
+        }
+        assert_eq!(microledger.blocks.len(), 3);
+
+        // Next block keeps serialization kind of the previous one
+        let block = microledger.pre_anchor_block(vec![identifier], &seals)?;
+        assert_eq!(block.serialization_kind(), SerializationKind::Mgpk);
+
+        // Version string is checked on anchoring, regardless of how the block
+        // was received
+        let mut missized = block.clone();
+        if let Some(version) = missized.version.as_mut() {
+            version.size += 1;
+        }
+        let signature = sign(missized.encode()?);
+        assert!(matches!(
+            microledger.anchor(missized.to_signed_block(vec![signature])),
+            Err(Error::VersionError(VersionError::WrongSize { .. }))
+        ));
+
+        // Declared serialization kind doesn't match the actual one
+        let block = block.with_serialization_kind(SerializationKind::Json)?;
+        let encoded = String::from_utf8(block.encode()?).unwrap();
+        let mislabeled = encoded.replace("MICR10JSON", "MICR10CBOR");
+        assert!(matches!(
+            Block::<EasyIdentifier>::from_slice(mislabeled.as_bytes()),
+            Err(Error::VersionError(VersionError::WrongKind { .. }))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_serialization() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+
+        // Properties are sorted, regardless of field order of the structure.
+        let block = Block::new(seals.get_fingerprints(), None, vec![identifier.clone()])?
+            .with_serialization_kind(SerializationKind::Jcs)?;
+        let encoded = String::from_utf8(block.encode()?).unwrap();
+        let expected = format!(
+            r#"{{"ci":["Identifier1"],"d":"{}","s":["{}"],"v":"MICR10JCSN{:06x}_"}}"#,
+            block.get_fingerprint()?,
+            block.seals[0].to_str(),
+            encoded.len()
+        );
+        assert_eq!(encoded, expected);
+
+        // Fingerprint is computed over canonical serialization with placeholder.
+        let placeholder = "#".repeat(block.get_fingerprint()?.to_string().len());
+        let mut value: serde_json::Value = serde_json::from_str(&encoded).unwrap();
+        value["d"] = serde_json::Value::String(placeholder);
+        let digest =
+            HashFunction::from(HashFunctionCode::Blake3_256).derive(&canonical::to_vec(&value)?);
+        assert_eq!(block.get_fingerprint()?, digest);
+
+        assert_eq!(
+            Block::<EasyIdentifier>::from_slice(encoded.as_bytes())?,
+            block
+        );
+        // Whitespace isn't allowed, even if declared size is correct
+        let spaced = encoded.replacen('{', "{ ", 1).replace(
+            &format!("{:06x}_", encoded.len()),
+            &format!("{:06x}_", encoded.len() + 1),
+        );
+        assert!(matches!(
+            Block::<EasyIdentifier>::from_slice(spaced.as_bytes()),
+            Err(Error::VersionError(VersionError::NotCanonical))
+        ));
+
+        // Ledger selects canonical serialization for all its blocks, genesis
+        // block included
+        let mut microledger = MicroLedger::new(validator);
+        let config = LedgerConfig {
+            serialization_kinds: vec!["JCSN".into()],
+            ..LedgerConfig::default()
+        };
+        let genesis = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_config(config)?;
+        assert_eq!(genesis.serialization_kind(), SerializationKind::Jcs);
+        let signature = sign(genesis.encode()?);
+        microledger.anchor(genesis.to_signed_block(vec![signature]))?;
+
+        let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+        assert_eq!(block.serialization_kind(), SerializationKind::Jcs);
+        let signature = sign(block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+
+        let block = microledger
+            .pre_anchor_block(vec![identifier], &seals)?
+            .with_serialization_kind(SerializationKind::Json)?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::ConfigError(
+                ConfigError::DisallowedSerializationKind(_)
+            ))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fingerprint_vectors() -> Result<()> {
+        // Fingerprints computed independently, over serialized blocks with
+        // fingerprint replaced by 44 `#` characters.
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = Block::new(
+            seals.get_fingerprints(),
+            None,
+            vec![EasyIdentifier("Identifier1".to_string())],
+        )?;
+        assert_eq!(
+            String::from_utf8(block.encode()?).unwrap(),
+            r#"{"v":"MICR10JSON000097_","ci":["Identifier1"],"d":"EDXAjVn9PsdYYsNk55oHGHXKqq6rqPg4Gb1oh0TRosVU","s":["AEOqPFj2zhoKSXkSRxeWNS7NQbvjBTreKhukIxWJKZyAP"]}"#
+        );
+
+        let block = block.with_serialization_kind(SerializationKind::Jcs)?;
+        assert_eq!(
+            String::from_utf8(block.encode()?).unwrap(),
+            r#"{"ci":["Identifier1"],"d":"ECd3w4_IfMlSFYGhFav8jQ8vheqehAayKJ5ZEPeAQw2K","s":["AEOqPFj2zhoKSXkSRxeWNS7NQbvjBTreKhukIxWJKZyAP"],"v":"MICR10JCSN000097_"}"#
+        );
+
+        // Binary blocks are encoded with field names, in the same order as
+        // JSON ones.
+        let from_hex = |hex: &str| -> Vec<u8> {
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect()
+        };
+        let block = block.with_serialization_kind(SerializationKind::Cbor)?;
+        assert_eq!(
+            block.get_fingerprint()?.to_string(),
+            "ELwX_DxsCegUyZWgTgjzFyiyGPE1WLCeLuDccuwEqO6Q"
+        );
+        assert_eq!(
+            block.encode()?,
+            from_hex(
+                "a46176714d494352313043424f523030303038375f626369816b4964656e746966696572316164782c454c77585f44787343656755795a576754676a7a4679697947504531574c43654c75446363757745714f3651617381782d41454f7150466a327a686f4b53586b53527865574e53374e5162766a425472654b68756b4978574a4b5a794150"
+            )
+        );
+        assert_eq!(Block::from_slice(&block.encode()?)?, block);
+
+        let block = block.with_serialization_kind(SerializationKind::Mgpk)?;
+        assert_eq!(
+            block.get_fingerprint()?.to_string(),
+            "ED7SJ2n4fVtb-1sFgItddm64wb0CI2hkRCyOsmANIjzk"
+        );
+        assert_eq!(
+            block.encode()?,
+            from_hex(
+                "84a176b14d49435231304d47504b3030303038375fa2636991ab4964656e74696669657231a164d92c454437534a326e34665674622d31734667497464646d3634776230434932686b5243794f736d414e496a7a6ba17391d92d41454f7150466a327a686f4b53586b53527865574e53374e5162766a425472654b68756b4978574a4b5a794150"
+            )
+        );
+        assert_eq!(Block::from_slice(&block.encode()?)?, block);
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_builder() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let mut microledger = MicroLedger::new(validator);
+
+        let genesis = BlockBuilder::new()
+            .with_seal_bundle(&seals)
+            .with_controlling_identifiers(vec![identifier.clone()])
+            .with_config(LedgerConfig {
+                name: Some("Builder ledger".into()),
+                ..LedgerConfig::default()
+            })
+            .with_threshold(1)
+            .with_metadata("schema", &"EBdXt3gIXOf2BBWNHdSXCJnFJL5OuQPyM5K0neuniccM")?
+            .with_hash_algorithm(HashFunctionCode::SHA3_256)
+            .with_previous_from(&microledger)?
+            .build()?;
+        assert_eq!(genesis.previous, None);
+        assert_eq!(genesis.sequence_number, Some(0));
+        assert_eq!(genesis.hash_algorithm(), HashFunctionCode::SHA3_256);
+        assert_eq!(genesis.config.as_ref().map(|c| c.threshold), Some(1));
+        assert_eq!(
+            genesis.get_metadata::<String>("schema")?.as_deref(),
+            Some("EBdXt3gIXOf2BBWNHdSXCJnFJL5OuQPyM5K0neuniccM")
+        );
+        let signature = sign(genesis.encode()?);
+        microledger.anchor(genesis.clone().to_signed_block(vec![signature]))?;
+
+        let block = BlockBuilder::new()
+            .with_seal_bundle(&seals)
+            .with_controlling_identifiers(vec![identifier.clone()])
+            .with_previous_from(&microledger)?
+            .build()?;
+        assert_eq!(block.previous, genesis.digital_fingerprint);
+        assert_eq!(block.ledger_id.as_ref(), microledger.id());
+        assert_eq!(block.sequence_number, Some(1));
+        // Hash algorithm is kept after the fingerprint is recomputed
+        let block = block.with_hash_algorithm(HashFunctionCode::SHA3_256)?;
+        assert_eq!(
+            block.clone().with_sequence_number(1)?.hash_algorithm(),
+            HashFunctionCode::SHA3_256
+        );
+        // Fingerprint of unknown digest algorithm is rejected
+        let fingerprint = block.get_fingerprint()?.to_string();
+        let unknown = String::from_utf8(block.encode()?)
+            .unwrap()
+            .replace(&fingerprint, &format!("Z{}", &fingerprint[1..]));
+        assert!(Block::<EasyIdentifier>::from_slice(unknown.as_bytes()).is_err());
+        let signature = sign(block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+
+        // Threshold amends current ledger configuration, whether it's set
+        // before the block is bound to the ledger or after.
+        for threshold_first in [true, false] {
+            let builder =
+                BlockBuilder::new().with_controlling_identifiers(vec![identifier.clone()]);
+            let builder = if threshold_first {
+                builder.with_threshold(1).with_previous_from(&microledger)?
+            } else {
+                builder.with_previous_from(&microledger)?.with_threshold(1)
+            };
+            assert_eq!(builder.build()?.config, Some(microledger.current_config()));
+        }
+
+        // Improper blocks are rejected before fingerprint is computed
+        assert!(matches!(
+            BlockBuilder::new()
+                .with_controlling_identifiers(vec![identifier.clone()])
+                .with_threshold(2)
+                .with_previous_from(&microledger)?
+                .build(),
+            Err(Error::ConfigError(ConfigError::ImproperThreshold))
+        ));
+        assert!(matches!(
+            BlockBuilder::new()
+                .with_controlling_identifiers(vec![identifier.clone()])
+                .with_validity(Some(Utc::now()), Some(Utc::now() - Duration::days(1)))
+                .build(),
+            Err(Error::BlockError(BlockError::WrongValidityWindow))
+        ));
+        assert!(matches!(
+            BlockBuilder::new()
+                .with_controlling_identifiers(vec![identifier])
+                .with_config(LedgerConfig {
+                    hash_algorithms: vec!["E".into()],
+                    ..LedgerConfig::default()
+                })
+                .with_hash_algorithm(HashFunctionCode::SHA3_256)
+                .build(),
+            Err(Error::ConfigError(ConfigError::DisallowedHashAlgorithm(_)))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_limits() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+        let limits = Limits {
+            max_block_size: 400,
+            max_seals: 2,
+            max_controlling_identifiers: 2,
+            max_attachment_size: 200,
+            ..Limits::default()
+        };
+        let mut microledger = MicroLedger::new(validator).with_limits(limits);
+        let seals = SealBundle::new()
+            .attach(SealData::AttachedData("one".into()))
+            .attach(SealData::AttachedData("two".into()))
+            .attach(SealData::AttachedData("three".into()));
+
+        let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::LimitError(LimitError::TooManySeals {
+                count: 3,
+                max: 2
+            }))
+        ));
+
+        let seals = SealBundle::new().attach(SealData::AttachedData("one".into()));
+        let controllers = (0..3)
+            .map(|i| EasyIdentifier(format!("Identifier{}", i)))
+            .collect();
+        let block = microledger.pre_anchor_block(controllers, &seals)?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::LimitError(LimitError::TooManyControllers {
+                count: 3,
+                max: 2
+            }))
+        ));
+
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_metadata("description", &"x".repeat(400))?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::LimitError(LimitError::BlockTooLarge {
+                max: 400,
+                ..
+            }))
+        ));
+
+        let block = microledger.pre_anchor_block(vec![identifier], &seals)?;
+        let signatures = vec![sign(block.encode()?); 3];
+        assert!(matches!(
+            microledger.anchor(block.clone().to_signed_block(signatures)),
+            Err(Error::LimitError(LimitError::AttachmentTooLarge {
+                max: 200,
+                ..
+            }))
+        ));
+        // Signatures are measured the same way when block is escrowed.
+        let signature = EdSignature("A".repeat(200));
+        assert!(matches!(
+            microledger.receive(block.clone().to_signed_block(vec![signature])),
+            Err(Error::LimitError(LimitError::AttachmentTooLarge {
+                max: 200,
+                ..
+            }))
+        ));
+        assert!(microledger.escrow().is_empty());
+        // and size of serialized block is checked before it's decoded.
+        assert!(matches!(
+            Block::<EasyIdentifier>::from_slice_with_limits(
+                &block.encode()?,
+                &Limits {
+                    max_block_size: 100,
+                    ..Limits::default()
+                }
+            ),
+            Err(Error::LimitError(LimitError::BlockTooLarge {
+                max: 100,
+                ..
+            }))
+        ));
+        let signature = sign(block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_partially_signed_block() -> Result<()> {
+        let signers = multi_controller_fixture(&["Controller0", "Controller1", "Controller2"]);
+        let (controllers, keypairs) = (&signers.identifiers, &signers.keypairs);
+        let validator = Arc::new(signers.verifier());
+
+        let mut microledger = MicroLedger::new(validator);
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = microledger
+            .pre_anchor_block(controllers.clone(), &seals)?
+            .with_config(LedgerConfig {
+                threshold: 2,
+                ..LedgerConfig::default()
+            })?;
+
+        let mut partially_signed = PartiallySignedBlock::new(block.clone());
+        assert!(partially_signed.add_signature(signers.sign(0, &block)?));
+        // Second signature of the same controller is ignored
+        assert!(!partially_signed.add_signature(signers.sign(0, &block)?));
+        assert_eq!(partially_signed.signatures().len(), 1);
+        assert_eq!(
+            partially_signed.missing_signers(&microledger)?,
+            controllers[1..].to_vec()
+        );
+        assert_eq!(partially_signed.remaining_signatures(&microledger)?, 1);
+        assert!(!partially_signed.is_complete(&microledger)?);
+        assert!(matches!(
+            partially_signed.clone().to_signed_block(&microledger),
+            Err(Error::ConfigError(ConfigError::ThresholdNotMet))
+        ));
+
+        // Signature which isn't valid doesn't count
+        let forged = AttributedSignature::new(&controllers[2], &keypairs[1], &block.encode()?);
+        assert!(partially_signed.add_signature(forged));
+        assert_eq!(partially_signed.remaining_signatures(&microledger)?, 1);
+        let mut partially_signed = PartiallySignedBlock::new(block.clone());
+        assert!(partially_signed.add_signature(signers.sign(0, &block)?));
+        assert!(partially_signed.add_signature(signers.sign(2, &block)?));
+        assert_eq!(
+            partially_signed.missing_signers(&microledger)?,
+            vec![controllers[1].clone()]
+        );
+        assert!(partially_signed.is_complete(&microledger)?);
+        microledger.anchor(partially_signed.to_signed_block(&microledger)?)?;
+
+        // Threshold applies to next blocks as well
+        let block = microledger.pre_anchor_block(controllers.clone(), &seals)?;
+        let mut partially_signed = PartiallySignedBlock::new(block.clone());
+        partially_signed.add_signature(signers.sign(1, &block)?);
+        assert_eq!(partially_signed.remaining_signatures(&microledger)?, 1);
+        partially_signed.add_signature(signers.sign(0, &block)?);
+        microledger.anchor(partially_signed.to_signed_block(&microledger)?)?;
+        assert_eq!(microledger.blocks.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_grouped_signatures() -> Result<()> {
+        let signers = multi_controller_fixture(&["Controller0", "Controller1", "Controller2"]);
+        let (controllers, keypairs) = (&signers.identifiers, &signers.keypairs);
+        let validator = Arc::new(signers.verifier());
+        let mut microledger = MicroLedger::new(validator);
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = microledger
+            .pre_anchor_block(controllers.clone(), &seals)?
+            .with_config(LedgerConfig {
+                threshold: 2,
+                ..LedgerConfig::default()
+            })?;
+
+        // Every signer of a group counts toward threshold
+        let group = signers.sign(0, &block)?.join(signers.sign(2, &block)?);
+        let signed = block.clone().to_signed_block(vec![group]);
+        assert_eq!(signed.count_signers(controllers), 2);
+        assert!(signed.check_controlling_identifiers(controllers)?);
+
+        // and is validated
+        let foreign = AttributedSignature::new(
+            &EasyIdentifier("Stranger".into()),
+            &keypairs[1],
+            &block.encode()?,
+        );
+        let group = signers.sign(0, &block)?.join(foreign);
+        assert!(!block
+            .clone()
+            .to_signed_block(vec![group])
+            .check_controlling_identifiers(controllers)?);
+
+        let group = signers.sign(0, &block)?.join(signers.sign(0, &block)?);
+        assert!(matches!(
+            microledger.anchor(
+                block
+                    .clone()
+                    .to_signed_block(vec![group, signers.sign(1, &block)?])
+            ),
+            Err(Error::BlockError(BlockError::DuplicatedSigner))
+        ));
+
+        // Signer unknown to the verifier is reported
+        let partial_validator = Arc::new(MultiVerifier(vec![
+            (controllers[0].clone(), keypairs[0].public),
+            (controllers[1].clone(), keypairs[1].public),
+        ]));
+        assert!(matches!(
+            MicroLedger::new(partial_validator).anchor(signed.clone()),
+            Err(Error::MicroError(MicroledgerError::InvalidSignature {
+                index: 0,
+                status: SignatureStatus::UnknownKey
+            }))
+        ));
+
+        microledger.anchor(signed)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_indexed_signatures() -> Result<()> {
+        let signers = multi_controller_fixture(&["Controller0", "Controller1", "Controller2"]);
+        let (controllers, keypairs) = (&signers.identifiers, &signers.keypairs);
+        let validator = Arc::new(CompactVerifier(signers.verifier()));
+        let mut microledger = MicroLedger::new(validator.clone());
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+
+        // Indexes of genesis block signatures point to its own controllers
+        let block = microledger
+            .pre_anchor_block(controllers.clone(), &seals)?
+            .with_config(LedgerConfig {
+                threshold: 2,
+                ..LedgerConfig::default()
+            })?;
+        microledger.anchor(block.clone().to_signed_block(vec![
+            signers.sign_indexed(0, &block)?,
+            signers.sign_indexed(2, &block)?,
+        ]))?;
+        // Signatures are stored as submitted, and resolved on demand.
+        assert!(microledger.blocks[0]
+            .signatures
+            .iter()
+            .all(|signature| signature.get_signers().is_empty()));
+        let resolved_signers: Vec<_> = microledger
+            .get_resolved_signatures(&block.get_fingerprint()?)?
+            .iter()
+            .flat_map(|signature| signature.get_signers())
+            .collect();
+        assert_eq!(
+            resolved_signers,
+            vec![controllers[0].clone(), controllers[2].clone()]
+        );
+
+        // Indexes of next blocks point to controllers of the previous block
+        let reordered = controllers.iter().rev().cloned().collect();
+        let block = microledger.pre_anchor_block(reordered, &seals)?;
+        let out_of_range = CompactSignature::indexed(3, &keypairs[1], &block.encode()?);
+        assert!(matches!(
+            microledger.anchor(
+                block
+                    .clone()
+                    .to_signed_block(vec![signers.sign_indexed(0, &block)?, out_of_range]),
+            ),
+            Err(Error::BlockError(BlockError::WrongSignatureIndex(3)))
+        ));
+        // Signature pointing to wrong controller doesn't verify
+        let misplaced = CompactSignature::indexed(2, &keypairs[1], &block.encode()?);
+        assert!(matches!(
+            microledger.anchor(
+                block
+                    .clone()
+                    .to_signed_block(vec![signers.sign_indexed(0, &block)?, misplaced]),
+            ),
+            Err(Error::MicroError(MicroledgerError::InvalidSignature {
+                index: 1,
+                status: SignatureStatus::Invalid
+            }))
+        ));
+        // Indexed signatures are resolved and verified before they count
+        // towards threshold.
+        let mut partially_signed = PartiallySignedBlock::new(block.clone());
+        partially_signed.add_signature(signers.sign_indexed(0, &block)?);
+        partially_signed.add_signature(CompactSignature::indexed(
+            2,
+            &keypairs[1],
+            &block.encode()?,
+        ));
+        assert_eq!(partially_signed.remaining_signatures(&microledger)?, 1);
+        // Out of range index is rejected the same way as on anchoring.
+        let mut out_of_range_signed = partially_signed.clone();
+        out_of_range_signed.add_signature(CompactSignature::indexed(
+            3,
+            &keypairs[1],
+            &block.encode()?,
+        ));
+        assert!(matches!(
+            out_of_range_signed.remaining_signatures(&microledger),
+            Err(Error::BlockError(BlockError::WrongSignatureIndex(3)))
+        ));
+        partially_signed.add_signature(signers.sign_indexed(1, &block)?);
+        assert!(partially_signed.is_complete(&microledger)?);
+        assert_eq!(
+            partially_signed.missing_signers(&microledger)?,
+            vec![controllers[2].clone()]
+        );
+        microledger.anchor(partially_signed.to_signed_block(&microledger)?)?;
+        assert_eq!(microledger.blocks.len(), 2);
+
+        // Escrowed block whose signature index turns out to be out of range
+        // is dropped once its predecessors arrive, without affecting them.
+        let mut receiver = MicroLedger::new(validator);
+        receiver.receive(microledger.blocks[0].clone())?;
+        let next = microledger.pre_anchor_block(controllers.clone(), &seals)?;
+        let out_of_range = CompactSignature::indexed(3, &keypairs[0], &next.encode()?);
+        assert!(receiver
+            .receive(next.to_signed_block(vec![out_of_range]))?
+            .is_empty());
+        assert_eq!(
+            receiver.receive(microledger.blocks[1].clone())?,
+            vec![microledger.blocks[1].block.get_fingerprint()?]
+        );
+        assert!(receiver.escrow().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_endorsements() -> Result<()> {
+        let signers = multi_controller_fixture(&["Controller", "Auditor0", "Auditor1"]);
+        let (identifiers, keypairs) = (&signers.identifiers, &signers.keypairs);
+        let validator = Arc::new(signers.verifier());
+        let controllers = vec![identifiers[0].clone()];
+        let mut microledger = MicroLedger::new(validator);
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+
+        // Block can be endorsed before it's anchored
+        let block = microledger.pre_anchor_block(controllers.clone(), &seals)?;
+        let signed = block
+            .clone()
+            .to_signed_block(vec![signers.sign(0, &block)?])
+            .with_endorsements(vec![signers.sign(1, &block)?]);
+        let fingerprint = block.get_fingerprint()?;
+        microledger.anchor(signed)?;
+        assert_eq!(
+            microledger
+                .get_block_by_fingerprint(&fingerprint)?
+                .endorsers(),
+            vec![identifiers[1].clone()]
+        );
+
+        // or afterwards, without changing its fingerprint
+        microledger.endorse(&fingerprint, signers.sign(2, &block)?)?;
+        assert_eq!(microledger.get_endorsements(&fingerprint)?.len(), 2);
+        assert_eq!(microledger.blocks[0].block.get_fingerprint()?, fingerprint);
+        assert!(microledger.blocks[0].signatures.len() == 1);
+
+        assert!(matches!(
+            microledger.endorse(&fingerprint, signers.sign(0, &block)?),
+            Err(Error::BlockError(BlockError::EndorsedByController))
+        ));
+        assert!(matches!(
+            microledger.endorse(&fingerprint, signers.sign(1, &block)?),
+            Err(Error::BlockError(BlockError::DuplicatedSigner))
+        ));
+        let forged = AttributedSignature::new(&identifiers[1], &keypairs[2], &block.encode()?);
+        let next = microledger.pre_anchor_block(controllers.clone(), &seals)?;
+        assert!(matches!(
+            microledger.anchor(
+                next.clone()
+                    .to_signed_block(vec![signers.sign(0, &next)?])
+                    .with_endorsements(vec![forged.clone()])
+            ),
+            Err(Error::MicroError(MicroledgerError::WrongEndorsement))
+        ));
+        assert!(microledger.endorse(&fingerprint, forged).is_err());
+        assert_eq!(microledger.get_endorsements(&fingerprint)?.len(), 2);
+
+        // Endorsements are kept apart from signatures when serialized
+        let serialized = serde_json::to_string(&microledger.blocks[0]).unwrap();
+        let deserialized: SignedBlock<EasyIdentifier, AttributedSignature> =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.endorsements.len(), 2);
+        assert_eq!(deserialized.signatures.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_witness_receipts() -> Result<()> {
+        let signers = multi_controller_fixture(&["Controller", "Witness0", "Witness1", "Stranger"]);
+        let identifiers = &signers.identifiers;
+        let validator = Arc::new(signers.verifier());
+        let receipt = |i: usize, fingerprint: &SelfAddressingIdentifier| {
+            Receipt::new(
+                identifiers[i].clone(),
+                signers.sign_data(i, fingerprint.to_string().as_bytes()),
+            )
+        };
+        let witnesses = identifiers[1..3].to_vec();
+        assert!(matches!(
+            MicroLedger::new(validator.clone()).with_witnesses(witnesses.clone(), 3),
+            Err(Error::ConfigError(ConfigError::ImproperThreshold))
+        ));
+        let mut microledger = MicroLedger::new(validator).with_witnesses(witnesses, 2)?;
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = microledger.pre_anchor_block(vec![identifiers[0].clone()], &seals)?;
+        let fingerprint = block.get_fingerprint()?;
+        let signature = signers.sign(0, &block)?;
+        microledger.anchor(
+            block
+                .to_signed_block(vec![signature])
+                .with_receipts(vec![receipt(1, &fingerprint)]),
+        )?;
+        assert!(!microledger.is_witnessed(&fingerprint)?);
+
+        assert!(matches!(
+            microledger.add_receipt(&fingerprint, receipt(3, &fingerprint)),
+            Err(Error::MicroError(MicroledgerError::UnknownWitness))
+        ));
+        // Receipt has to be signed by its witness
+        let forged = Receipt::new(identifiers[2].clone(), receipt(1, &fingerprint).signature);
+        assert!(matches!(
+            microledger.add_receipt(&fingerprint, forged),
+            Err(Error::MicroError(MicroledgerError::WrongReceipt))
+        ));
+        assert!(!microledger.add_receipt(&fingerprint, receipt(1, &fingerprint))?);
+        assert!(!microledger.is_witnessed(&fingerprint)?);
+
+        assert!(microledger.add_receipt(&fingerprint, receipt(2, &fingerprint))?);
+        assert!(microledger.is_witnessed(&fingerprint)?);
+        assert_eq!(microledger.get_receipts(&fingerprint)?.len(), 2);
+        assert_eq!(microledger.blocks[0].block.get_fingerprint()?, fingerprint);
+        Ok(())
+    }
+
+    #[test]
+    fn test_escrow() -> Result<()> {
+        let signers = multi_controller_fixture(&["Controller0", "Controller1"]);
+        let (controllers, keypairs) = (&signers.identifiers, &signers.keypairs);
+        let validator = Arc::new(signers.verifier());
+        let clock = Arc::new(TestClock(Mutex::new(Utc::now())));
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+
+        // Blocks are created in one ledger and received by the other one.
+        let mut source = MicroLedger::new(validator.clone()).with_clock(clock.clone());
+        let mut blocks = vec![];
+        for i in 0..3 {
+            let mut block = source.pre_anchor_block(controllers.clone(), &seals)?;
+            if i == 0 {
+                block = block.with_config(LedgerConfig {
+                    threshold: 2,
+                    ..LedgerConfig::default()
+                })?;
+            }
+            let signed = block
+                .clone()
+                .to_signed_block(vec![signers.sign(0, &block)?, signers.sign(1, &block)?]);
+            source.anchor(signed.clone())?;
+            blocks.push(signed);
+        }
+
+        let mut microledger = MicroLedger::new(validator.clone())
+            .with_clock(clock.clone())
+            .with_escrow_timeout(Duration::minutes(5));
+        assert!(microledger.receive(blocks[2].clone())?.is_empty());
+        assert!(microledger.receive(blocks[1].clone())?.is_empty());
+        assert_eq!(microledger.escrow().len(), 2);
+        let escrowed = microledger
+            .escrow()
+            .get(&blocks[1].block.get_fingerprint()?)
+            .unwrap();
+        assert_eq!(escrowed.reason, EscrowReason::MissingPrevious);
+        assert!(microledger.blocks.is_empty());
+
+        let anchored = microledger.receive(blocks[0].clone())?;
+        assert_eq!(
+            anchored,
+            blocks
+                .iter()
+                .map(|b| b.block.get_fingerprint())
+                .collect::<Result<Vec<_>>>()?
+        );
+        assert!(microledger.escrow().is_empty());
+        assert_eq!(microledger.blocks.len(), 3);
+
+        // Signatures received separately are merged
+        let block = microledger.pre_anchor_block(controllers.clone(), &seals)?;
+        let first_half = block
+            .clone()
+            .to_signed_block(vec![signers.sign(0, &block)?]);
+        let second_half = block
+            .clone()
+            .to_signed_block(vec![signers.sign(1, &block)?]);
+        source.anchor(
+            block
+                .clone()
+                .to_signed_block(vec![signers.sign(0, &block)?, signers.sign(1, &block)?]),
+        )?;
+        assert!(microledger.receive(first_half)?.is_empty());
+        let escrowed = microledger.escrow().get(&block.get_fingerprint()?).unwrap();
+        assert_eq!(escrowed.reason, EscrowReason::MissingSignatures);
+        assert_eq!(
+            microledger.receive(second_half)?,
+            vec![block.get_fingerprint()?]
+        );
+
+        // Blocks waiting too long are dropped
+        let block = source.pre_anchor_block(controllers.clone(), &seals)?;
+        source.anchor(
+            block
+                .clone()
+                .to_signed_block(vec![signers.sign(0, &block)?, signers.sign(1, &block)?]),
+        )?;
+        let next = source.pre_anchor_block(controllers.clone(), &seals)?;
+        let out_of_order = next
+            .clone()
+            .to_signed_block(vec![signers.sign(0, &next)?, signers.sign(1, &next)?]);
+        assert!(microledger.receive(out_of_order)?.is_empty());
+        clock.advance(Duration::minutes(10));
+        let expired = microledger.prune_escrow();
+        assert_eq!(expired.len(), 1);
+        assert!(microledger.escrow().is_empty());
+
+        // Invalid block is reported
+        let wrong_signature = block
+            .clone()
+            .to_signed_block(vec![signers.sign(0, &next)?, signers.sign(1, &block)?]);
+        assert!(microledger.receive(wrong_signature).is_err());
+        assert!(microledger.escrow().is_empty());
+
+        // Forged signature is rejected before it's escrowed, so it can't take
+        // place of the valid one.
+        let forged = AttributedSignature::new(&controllers[1], &keypairs[0], &block.encode()?);
+        assert!(matches!(
+            microledger.receive(
+                block
+                    .clone()
+                    .to_signed_block(vec![signers.sign(0, &block)?, forged])
+            ),
+            Err(Error::MicroError(MicroledgerError::InvalidSignature {
+                index: 1,
+                status: SignatureStatus::Invalid
+            }))
+        ));
+        assert!(microledger.escrow().is_empty());
+        let first_half = block
+            .clone()
+            .to_signed_block(vec![signers.sign(0, &block)?]);
+        assert!(microledger.receive(first_half)?.is_empty());
+        let second_half = block
+            .clone()
+            .to_signed_block(vec![signers.sign(1, &block)?]);
+        assert_eq!(
+            microledger.receive(second_half)?,
+            vec![block.get_fingerprint()?]
+        );
+
+        // Escrow is bounded, blocks already waiting aren't pushed out by new
+        // ones
+        let mut microledger = MicroLedger::new(validator).with_limits(Limits {
+            max_escrowed_blocks: 1,
+            ..Limits::default()
+        });
+        assert!(microledger.receive(blocks[2].clone())?.is_empty());
+        assert!(matches!(
+            microledger.receive(blocks[1].clone()),
+            Err(Error::LimitError(LimitError::EscrowFull { max: 1 }))
+        ));
+        assert_eq!(microledger.escrow().len(), 1);
+        assert!(microledger
+            .escrow()
+            .get(&blocks[2].block.get_fingerprint()?)
+            .is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_signature_sets() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+        let mut microledger = MicroLedger::new(validator);
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+
+        assert!(matches!(
+            microledger.anchor(block.clone().to_signed_block(vec![])),
+            Err(Error::BlockError(BlockError::MissingSignatures))
+        ));
+
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(
+                block
+                    .clone()
+                    .to_signed_block(vec![signature.clone(), signature.clone()])
+            ),
+            Err(Error::BlockError(BlockError::DuplicatedSigner))
+        ));
+
+        #[derive(Clone)]
+        struct AnonymousSignature;
+        impl Signature for AnonymousSignature {
+            type Identifier = EasyIdentifier;
+
+            fn get_signers(&self) -> Vec<Self::Identifier> {
+                vec![]
+            }
+        }
+        let anonymous = block.clone().to_signed_block(vec![AnonymousSignature]);
+        assert!(matches!(
+            anonymous.check_controlling_identifiers(&[identifier]),
+            Err(Error::BlockError(BlockError::UnattributableSignatures))
+        ));
+
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_metadata() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+
+        // Every block needs to point its schema
+        let schema_policy = |block: &Block<EasyIdentifier>| -> Result<()> {
+            match block.get_metadata::<String>("schema")? {
+                Some(_) => Ok(()),
+                None => Err(MicroledgerError::PolicyViolation("Missing schema".into()).into()),
+            }
+        };
+        let mut microledger =
+            MicroLedger::new(validator.clone()).with_policy(Arc::new(schema_policy));
+
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::PolicyViolation(_)))
+        ));
+
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_metadata("schema", &"EBdXt3gIXOf2BBWNHdSXCJnFJL5OuQPyM5K0neuniccM")?
+            .with_metadata("tags", &vec!["invoice", "2023"])?;
+        let deserialized_block: Block<EasyIdentifier> =
+            serde_json::from_slice(&block.encode()?).unwrap();
+        assert_eq!(block, deserialized_block);
+        assert_eq!(
+            deserialized_block.get_metadata::<Vec<String>>("tags")?,
+            Some(vec!["invoice".to_string(), "2023".to_string()])
+        );
+        assert_eq!(deserialized_block.get_metadata::<String>("missing")?, None);
+
+        let signature = sign(block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+
+        // Other errors of policies are reported as violations too.
+        let numeric_schema_policy = |block: &Block<EasyIdentifier>| -> Result<()> {
+            block.get_metadata::<u64>("schema").map(|_| ())
+        };
+        let mut microledger =
+            MicroLedger::new(validator).with_policy(Arc::new(numeric_schema_policy));
+        let block = microledger
+            .pre_anchor_block(vec![identifier], &seals)?
+            .with_metadata("schema", &"EBdXt3gIXOf2BBWNHdSXCJnFJL5OuQPyM5K0neuniccM")?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::PolicyViolation(_)))
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn test_microledger() -> Result<()> {
         // generate keypair and setup verifier
@@ -94,7 +1310,10 @@ pub mod test {
             .to_signed_block(vec![EdSignature(b64_signature)]);
         assert!(matches!(
             microledger.anchor(signed),
-            Err(Error::MicroError(MicroledgerError::WrongBlock))
+            Err(Error::MicroError(MicroledgerError::InvalidSignature {
+                index: 0,
+                status: SignatureStatus::Invalid
+            }))
         ));
 
         assert!(microledger.blocks.is_empty());
@@ -134,4 +1353,673 @@ pub mod test {
 
         Ok(())
     }
+
+    #[cfg(feature = "ed25519")]
+    #[test]
+    fn test_ed25519_microledger() -> Result<()> {
+        use microledger::ed25519::{Ed25519Identifier, Ed25519Signature, Ed25519Verifier};
+
+        let kp = ed25519_dalek::Keypair::generate(&mut OsRng {});
+        let identifier = Ed25519Identifier::from(kp.public);
+        let mut microledger = MicroLedger::new(Arc::new(Ed25519Verifier));
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+
+        let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+        let signed = block
+            .clone()
+            .to_signed_block(vec![Ed25519Signature::sign(&kp, b"Wrong signature")]);
+        assert!(matches!(
+            microledger.anchor(signed),
+            Err(Error::MicroError(MicroledgerError::InvalidSignature {
+                index: 0,
+                status: SignatureStatus::Invalid
+            }))
+        ));
+
+        // Signature of other key doesn't name controller of the block
+        let stranger = ed25519_dalek::Keypair::generate(&mut OsRng {});
+        let signed = block
+            .clone()
+            .to_signed_block(vec![Ed25519Signature::sign(&stranger, &block.encode()?)]);
+        assert!(matches!(
+            microledger.anchor(signed),
+            Err(Error::MicroError(MicroledgerError::WrongSigner))
+        ));
+
+        let signature = Ed25519Signature::sign(&kp, &block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+        assert_eq!(microledger.blocks.len(), 1);
+
+        let serialized = serde_json::to_string(&microledger.blocks).unwrap();
+        let deserialized: Vec<SignedBlock<Ed25519Identifier, Ed25519Signature>> =
+            serde_json::from_str(&serialized).unwrap();
+        assert!(deserialized[0].verify(Arc::new(Ed25519Verifier))?);
+
+        // Identifier of loaded ledger is derived from its genesis block, and
+        // every other block has to carry it.
+        let block = microledger.pre_anchor_block(vec![identifier], &seals)?;
+        let signature = Ed25519Signature::sign(&kp, &block.encode()?);
+        microledger.anchor(block.clone().to_signed_block(vec![signature]))?;
+        type Ledger = MicroLedger<Ed25519Signature, Ed25519Verifier, Ed25519Identifier>;
+        let exported = serde_json::to_value(&microledger).unwrap();
+        let loaded: Ledger = serde_json::from_value(exported.clone()).unwrap();
+        assert_eq!(loaded.id(), microledger.id());
+        assert_eq!(loaded.blocks.len(), 2);
+
+        let mut wrong_id = exported.clone();
+        wrong_id["i"] = serde_json::to_value(&block.digital_fingerprint).unwrap();
+        assert!(serde_json::from_value::<Ledger>(wrong_id).is_err());
+        let mut missing_id = exported;
+        missing_id["bs"][1]["block"]
+            .as_object_mut()
+            .unwrap()
+            .remove("i");
+        assert!(serde_json::from_value::<Ledger>(missing_id).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "did")]
+    #[test]
+    fn test_did_key_microledger() -> Result<()> {
+        use microledger::{
+            did::key::{DidKey, DidKeySignature, DidKeyVerifier},
+            ed25519::{Ed25519Identifier, Ed25519Signature},
+            verifier::Verifier,
+        };
+
+        let keypair = ed25519_dalek::Keypair::generate(&mut OsRng {});
+        let sign = |data: &[u8]| DidKeySignature::from(Ed25519Signature::sign(&keypair, data));
+        let identifier = DidKey::from(Ed25519Identifier::from(keypair.public));
+
+        let mut microledger = MicroLedger::new(Arc::new(DidKeyVerifier));
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+        let signature = sign(&block.encode()?);
+        let serialized = serde_json::to_string(&signature)?;
+        assert!(serialized.contains(&format!(r#""i":"{}","s":"u"#, identifier)));
+        assert_eq!(
+            serde_json::from_str::<DidKeySignature>(&serialized)?,
+            signature
+        );
+
+        let rebuilt = DidKeySignature::new(identifier.clone(), signature.signature().to_vec());
+        let other = sign(b"other data");
+        assert_eq!(
+            DidKeyVerifier.verify(&block.encode()?, vec![rebuilt, other])?,
+            vec![SignatureStatus::Valid, SignatureStatus::Invalid]
+        );
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+        assert_eq!(microledger.blocks.len(), 1);
+        Ok(())
+    }
+
+    #[cfg(feature = "did")]
+    #[test]
+    fn test_did_verifier() -> Result<()> {
+        use ed25519_dalek::Keypair;
+        use microledger::{
+            did::{
+                key::{DidKey, DidPublicKey},
+                resolver::{DidDocument, MemoryResolver, Resolver},
+                verifier::DidVerifier,
+                Did, DidError, DidSignature,
+            },
+            verifier::Verifier,
+        };
+
+        struct SharedResolver(Arc<Mutex<MemoryResolver>>);
+
+        impl Resolver for SharedResolver {
+            fn resolve(&self, did: &Did) -> Result<Option<DidDocument>> {
+                if did.to_string() == "did:web:unreachable.example" {
+                    return Err(DidError::DocumentsUnavailable(did.to_string()).into());
+                }
+                self.0.lock().unwrap().resolve(did)
+            }
+        }
+
+        let generate = || Keypair::generate(&mut OsRng {});
+        let sign = |signer: &Did, keypair: &Keypair, data: &[u8]| {
+            DidSignature::new(signer.clone(), keypair.sign(data).to_bytes().to_vec())
+        };
+        let (old_keypair, new_keypair, key_keypair) = (generate(), generate(), generate());
+        let web: Did = "did:web:example.com".parse()?;
+        let key: Did = DidKey::from(DidPublicKey::Ed25519(key_keypair.public)).into();
+
+        let documents = Arc::new(Mutex::new(MemoryResolver::new().with_document(
+            DidDocument::new(web.clone()).with_key(&DidPublicKey::Ed25519(old_keypair.public)),
+        )));
+        let verifier = Arc::new(DidVerifier::new(SharedResolver(documents.clone())));
+        let genesis_time = Utc::now();
+        let clock = Arc::new(TestClock(Mutex::new(genesis_time)));
+        let mut microledger = MicroLedger::new(verifier.clone()).with_clock(clock.clone());
+
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = microledger.pre_anchor_block(vec![web.clone(), key.clone()], &seals)?;
+        let data = block.encode()?;
+        // Signers which can't be resolved get their own statuses.
+        let unknown = "did:web:example.org".parse()?;
+        let unreachable = "did:web:unreachable.example".parse()?;
+        let x25519 = "did:key:z6LSeu9HkTHSfLLeUs2nnzUSNedgDUevfNQgQjQC23ZCit6F".parse()?;
+        assert_eq!(
+            verifier.verify(
+                &data,
+                vec![
+                    sign(&web, &new_keypair, &data),
+                    sign(&unknown, &old_keypair, &data),
+                    sign(&unreachable, &old_keypair, &data),
+                    sign(&x25519, &old_keypair, &data),
+                ]
+            )?,
+            vec![
+                SignatureStatus::Invalid,
+                SignatureStatus::UnknownKey,
+                SignatureStatus::UnknownKey,
+                SignatureStatus::UnsupportedType
+            ]
+        );
+        let signatures = vec![
+            sign(&web, &old_keypair, &data),
+            sign(&key, &key_keypair, &data),
+        ];
+        microledger.anchor(block.to_signed_block(signatures.clone()))?;
+        // Keys of did:key identifiers can't change, so only did:web key is
+        // recorded.
+        let key_states = serde_json::to_value(verifier.key_states())?;
+        let recorded = DidKey::from(DidPublicKey::Ed25519(old_keypair.public)).to_string();
+        let recorded_keys: Vec<_> = key_states.as_object().unwrap().values().collect();
+        assert_eq!(
+            recorded_keys,
+            vec![&serde_json::json!({ web.to_string(): recorded })]
+        );
+
+        // Rotate key of did:web controller
+        clock.advance(Duration::minutes(1));
+        documents.lock().unwrap().insert(
+            DidDocument::new(web.clone()).with_key(&DidPublicKey::Ed25519(new_keypair.public)),
+        );
+        // Anchored block is still verified against keys which verified it
+        // when it was anchored, also by verifier restored from stored key
+        // states.
+        assert_eq!(
+            verifier.verify(&data, signatures.clone())?,
+            vec![SignatureStatus::Valid; 2]
+        );
+        assert_eq!(
+            verifier.verify(&data, vec![sign(&web, &new_keypair, &data)])?,
+            vec![SignatureStatus::Invalid]
+        );
+        let stored = serde_json::to_string(&verifier.key_states())?;
+        let restored = DidVerifier::new(SharedResolver(documents.clone()))
+            .with_key_states(serde_json::from_str(&stored)?);
+        assert_eq!(
+            restored.verify(&data, signatures.clone())?,
+            vec![SignatureStatus::Valid; 2]
+        );
+        let fresh_verifier = DidVerifier::new(SharedResolver(documents.clone()));
+        assert_eq!(
+            fresh_verifier.verify(&data, signatures)?,
+            vec![SignatureStatus::Invalid, SignatureStatus::Valid]
+        );
+
+        // New block is verified against current keys, whatever its timestamp.
+        let seals = SealBundle::new().attach(SealData::AttachedData("world".into()));
+        let backdated = BlockBuilder::new()
+            .with_previous_from(&microledger)?
+            .with_timestamp(genesis_time)
+            .with_controlling_identifiers(vec![web.clone()])
+            .with_seal_bundle(&seals)
+            .build()?;
+        let data = backdated.encode()?;
+        assert!(matches!(
+            microledger.anchor(backdated.to_signed_block(vec![
+                sign(&web, &old_keypair, &data),
+                sign(&key, &key_keypair, &data)
+            ])),
+            Err(Error::MicroError(MicroledgerError::InvalidSignature {
+                index: 0,
+                status: SignatureStatus::Invalid
+            }))
+        ));
+        let block = microledger.pre_anchor_block(vec![web.clone()], &seals)?;
+        let data = block.encode()?;
+        microledger.anchor(block.to_signed_block(vec![
+            sign(&web, &new_keypair, &data),
+            sign(&key, &key_keypair, &data),
+        ]))?;
+        assert_eq!(microledger.blocks.len(), 2);
+        assert_eq!(
+            serde_json::to_value(verifier.key_states())?
+                .as_object()
+                .map(|states| states.len()),
+            Some(2)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "did")]
+    #[test]
+    fn test_composite_verifier() -> Result<()> {
+        use ed25519_dalek::Keypair;
+        use microledger::{
+            composite::{CompositeIdentifier, CompositeSignature, CompositeVerifier},
+            did::{
+                key::DidPublicKey,
+                resolver::{DidDocument, MemoryResolver},
+                verifier::DidVerifier,
+                Did, DidSignature,
+            },
+            ed25519::{Ed25519Identifier, Ed25519Signature},
+            verifier::Verifier,
+        };
+
+        let (ed_keypair, did_keypair) = (
+            Keypair::generate(&mut OsRng {}),
+            Keypair::generate(&mut OsRng {}),
+        );
+        let ed: CompositeIdentifier = Ed25519Identifier::from(ed_keypair.public).into();
+        let web: Did = "did:web:example.com".parse()?;
+        let resolver = MemoryResolver::new().with_document(
+            DidDocument::new(web.clone()).with_key(&DidPublicKey::Ed25519(did_keypair.public)),
+        );
+        let sign = |data: &[u8]| -> Vec<CompositeSignature> {
+            vec![
+                Ed25519Signature::sign(&ed_keypair, data).into(),
+                DidSignature::new(web.clone(), did_keypair.sign(data).to_bytes().to_vec()).into(),
+            ]
+        };
+
+        let did_verifier = Arc::new(DidVerifier::new(resolver));
+        let verifier = CompositeVerifier::new().with_did(did_verifier.clone());
+        let mut microledger = MicroLedger::new(Arc::new(verifier));
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = microledger.pre_anchor_block(vec![ed, web.clone().into()], &seals)?;
+        let encoded = block.encode()?;
+        let serialized = String::from_utf8(encoded.clone()).unwrap();
+        assert!(serialized.contains(r#"{"ed25519":"D"#));
+        assert!(serialized.contains(r#"{"did":"did:web:example.com"}"#));
+        assert_eq!(Block::<CompositeIdentifier>::from_slice(&encoded)?, block);
+
+        // DID signatures aren't verified without DID verifier
+        assert_eq!(
+            CompositeVerifier::new().verify(&encoded, sign(&encoded))?,
+            vec![SignatureStatus::Valid, SignatureStatus::UnsupportedType]
+        );
+
+        let mut signatures = sign(&encoded);
+        signatures.swap(0, 1);
+        signatures[1] = Ed25519Signature::sign(&ed_keypair, b"other data").into();
+        assert!(matches!(
+            microledger.anchor(block.clone().to_signed_block(signatures)),
+            Err(Error::MicroError(MicroledgerError::InvalidSignature {
+                index: 1,
+                status: SignatureStatus::Invalid
+            }))
+        ));
+        microledger.anchor(block.to_signed_block(sign(&encoded)))?;
+        assert_eq!(microledger.blocks.len(), 1);
+        // Keys of accepted DID signatures are recorded by DID verifier.
+        assert_ne!(did_verifier.key_states(), Default::default());
+        Ok(())
+    }
+
+    #[test]
+    fn test_genesis_config() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+        let mut microledger = MicroLedger::new(validator);
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+
+        // Threshold can't exceed number of controllers
+        let config = LedgerConfig {
+            name: Some("Test ledger".into()),
+            hash_algorithms: vec!["E".into()],
+            seal_types: vec!["A".into()],
+            serialization_kinds: vec![],
+            threshold: 2,
+        };
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_config(config.clone())?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::ConfigError(ConfigError::ImproperThreshold))
+        ));
+
+        let config = LedgerConfig {
+            threshold: 1,
+            ..config
+        };
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_config(config.clone())?;
+        let signature = sign(block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+        assert_eq!(microledger.current_config(), config);
+
+        // Amend configuration to allow only SHA3-256 digests
+        let amended = LedgerConfig {
+            hash_algorithms: vec!["H".into()],
+            ..config
+        };
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_config(amended.clone())?;
+        let signature = sign(block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+        assert_eq!(microledger.current_config(), amended);
+
+        // Blake3-256 digests are not allowed anymore
+        let block = microledger.pre_anchor_block(vec![identifier], &seals)?;
+        // and entries which aren't digest codes don't match their prefixes.
+        let partial = LedgerConfig {
+            hash_algorithms: vec!["".into(), "0".into()],
+            ..LedgerConfig::default()
+        };
+        assert!(matches!(
+            partial.check_hash_algorithm(&block.get_fingerprint()?),
+            Err(Error::ConfigError(ConfigError::DisallowedHashAlgorithm(_)))
+        ));
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::ConfigError(ConfigError::DisallowedHashAlgorithm(_)))
+        ));
+        assert_eq!(microledger.blocks.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ledger_identifier() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+
+        let mut microledger = MicroLedger::new(validator.clone());
+        assert!(microledger.id().is_none());
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let genesis = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+        assert!(genesis.ledger_id.is_none());
+        let signature = sign(genesis.encode()?);
+        microledger.anchor(genesis.clone().to_signed_block(vec![signature]))?;
+        assert_eq!(microledger.id(), genesis.digital_fingerprint.as_ref());
+
+        let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+        assert_eq!(block.ledger_id.as_ref(), microledger.id());
+
+        // Block bound to one ledger can't be anchored in the other one
+        let mut other_microledger = MicroLedger::new(validator);
+        let other_seals = SealBundle::new().attach(SealData::AttachedData("other".into()));
+        let other_genesis =
+            other_microledger.pre_anchor_block(vec![identifier.clone()], &other_seals)?;
+        let signature = sign(other_genesis.encode()?);
+        other_microledger.anchor(other_genesis.to_signed_block(vec![signature]))?;
+
+        let signature = sign(block.encode()?);
+        let signed = block.to_signed_block(vec![signature]);
+        assert!(matches!(
+            other_microledger.anchor(signed.clone()),
+            Err(Error::MicroError(MicroledgerError::WrongLedger))
+        ));
+        microledger.anchor(signed)?;
+
+        // Blocks other than genesis have to name their ledger.
+        let block = Block::new(
+            seals.get_fingerprints(),
+            microledger
+                .get_last_block()
+                .map(Block::get_fingerprint)
+                .transpose()?,
+            vec![identifier],
+        )?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::MissingLedgerId))
+        ));
+
+        let exported = serde_json::to_value(&microledger).unwrap();
+        assert_eq!(
+            exported["i"],
+            serde_json::to_value(microledger.id()).unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequence_numbers() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+
+        let mut microledger = MicroLedger::new(validator);
+        for data in ["one", "two", "three"] {
+            let seals = SealBundle::new().attach(SealData::AttachedData(data.into()));
+            let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+            assert_eq!(block.sequence_number, Some(microledger.blocks.len() as u64));
+            let signature = sign(block.encode()?);
+            microledger.anchor(block.to_signed_block(vec![signature]))?;
+        }
+
+        let second = microledger.get_block_by_sn(1)?;
+        assert_eq!(second.block, microledger.blocks[1].block);
+        assert!(matches!(
+            microledger.get_block_by_sn(3),
+            Err(Error::MicroError(MicroledgerError::MissingSequenceNumber(
+                3
+            )))
+        ));
+
+        let at_second = microledger.at_sn(1).unwrap();
+        assert_eq!(at_second.blocks.len(), 2);
+        assert_eq!(at_second.get_last_block(), Some(&second.block));
+        assert!(microledger.at_sn(3).is_none());
+
+        // Sequence number has to directly follow the last block one
+        let seals = SealBundle::new().attach(SealData::AttachedData("four".into()));
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_sequence_number(5)?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::WrongSequenceNumber(5)))
+        ));
+
+        // and versioned block can't omit it.
+        let block = microledger.pre_anchor_block(vec![identifier], &seals)?;
+        let block = Block {
+            sequence_number: None,
+            ..block
+        }
+        .with_hash_algorithm(HashFunctionCode::Blake3_256)?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.clone().to_signed_block(vec![signature.clone()])),
+            Err(Error::MicroError(MicroledgerError::UnnumberedBlock))
+        ));
+        assert!(matches!(
+            microledger.receive(block.to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::UnnumberedBlock))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_timestamps() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+
+        let start = Utc::now();
+        let clock = Arc::new(TestClock(Mutex::new(start)));
+        let mut microledger = MicroLedger::new(validator)
+            .with_clock(clock.clone())
+            .with_max_clock_skew(Duration::seconds(5));
+
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        for _ in 0..3 {
+            let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+            assert_eq!(block.get_timestamp()?, Some(clock.now()));
+            let signature = sign(block.encode()?);
+            microledger.anchor(block.to_signed_block(vec![signature]))?;
+            clock.advance(Duration::minutes(1));
+        }
+
+        // Block created with clock that is too far behind
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_timestamp(start)?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::TimestampBeforePrevious))
+        ));
+
+        // Block created with clock that is too far ahead
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_timestamp(clock.now() + Duration::minutes(1))?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::TimestampInFuture))
+        ));
+
+        // Difference within clock skew is accepted
+        let block = microledger
+            .pre_anchor_block(vec![identifier], &seals)?
+            .with_timestamp(clock.now() + Duration::seconds(3))?;
+        let signature = sign(block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+
+        assert!(microledger.at_time(start - Duration::seconds(1))?.is_none());
+        let state = microledger.at_time(start + Duration::seconds(90))?.unwrap();
+        assert_eq!(state.blocks.len(), 2);
+        let state = microledger.at_time(clock.now())?.unwrap();
+        assert_eq!(state.blocks.len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validity_windows() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+
+        let start = Utc::now();
+        let clock = Arc::new(TestClock(Mutex::new(start)));
+        let mut microledger = MicroLedger::new(validator).with_clock(clock.clone());
+
+        let seals = SealBundle::new().attach(SealData::AttachedData("one day".into()));
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_validity(Some(start), Some(start + Duration::days(1)))?;
+        let signature = sign(block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+
+        // Time-locked block can't be anchored before its window starts
+        let locked_seals = SealBundle::new().attach(SealData::AttachedData("locked".into()));
+        let locked = microledger
+            .pre_anchor_block(vec![identifier.clone()], &locked_seals)?
+            .with_validity(Some(start + Duration::hours(1)), None)?;
+        let signature = sign(locked.encode()?);
+        assert!(matches!(
+            microledger.anchor(locked.clone().to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::NotYetValid))
+        ));
+
+        clock.advance(Duration::hours(2));
+        let signature = sign(locked.encode()?);
+        microledger.anchor(locked.to_signed_block(vec![signature]))?;
+
+        // Expired block can't be anchored
+        let block = microledger
+            .pre_anchor_block(vec![identifier.clone()], &seals)?
+            .with_validity(None, Some(start + Duration::hours(1)))?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::Expired))
+        ));
+
+        let mut valid_seals = seals.get_fingerprints();
+        valid_seals.extend(locked_seals.get_fingerprints());
+        assert_eq!(microledger.valid_seals()?, valid_seals);
+
+        clock.advance(Duration::days(1));
+        assert_eq!(microledger.valid_seals()?, locked_seals.get_fingerprints());
+        assert_eq!(microledger.valid_seals_at(start)?, seals.get_fingerprints());
+
+        // Block which expires while waiting in escrow for its predecessor is
+        // still anchored, as it was valid when received.
+        let mut ahead = microledger
+            .at_sn(microledger.blocks.len() as u64 - 1)
+            .unwrap();
+        let previous = ahead.pre_anchor_block(vec![identifier.clone()], &seals)?;
+        let previous_signature = sign(previous.encode()?);
+        ahead.anchor(
+            previous
+                .clone()
+                .to_signed_block(vec![previous_signature.clone()]),
+        )?;
+        let expiring = ahead
+            .pre_anchor_block(vec![identifier], &seals)?
+            .with_validity(None, Some(clock.now() + Duration::minutes(10)))?;
+        let signature = sign(expiring.encode()?);
+        assert!(microledger
+            .receive(expiring.to_signed_block(vec![signature]))?
+            .is_empty());
+        clock.advance(Duration::minutes(30));
+        assert_eq!(
+            microledger
+                .receive(previous.to_signed_block(vec![previous_signature]))?
+                .len(),
+            2
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_microledger_termination() -> Result<()> {
+        let (validator, sign, identifier) = ed25519_fixture();
+        let mut microledger = MicroLedger::new(validator);
+        let seals = SealBundle::new().attach(SealData::AttachedData("hello".into()));
+        let block = microledger.pre_anchor_block(vec![identifier.clone()], &seals)?;
+        let signature = sign(block.encode()?);
+        microledger.anchor(block.to_signed_block(vec![signature]))?;
+        assert!(!microledger.is_terminated());
+
+        let terminal = microledger.pre_terminate_block(&SealBundle::new())?;
+        let terminal_id = terminal.get_fingerprint()?;
+        let signature = sign(terminal.encode()?);
+        microledger.anchor(terminal.to_signed_block(vec![signature]))?;
+        assert!(microledger.is_terminated());
+        assert_eq!(
+            microledger
+                .terminal_block()
+                .unwrap()
+                .block
+                .get_fingerprint()?,
+            terminal_id
+        );
+
+        // Nothing can be anchored after terminal block
+        let block = microledger.pre_anchor_block(vec![identifier], &seals)?;
+        let signature = sign(block.encode()?);
+        assert!(matches!(
+            microledger.anchor(block.to_signed_block(vec![signature])),
+            Err(Error::MicroError(MicroledgerError::Terminated(id))) if id == terminal_id
+        ));
+        assert_eq!(microledger.blocks.len(), 2);
+
+        // Last block without fingerprint is reported instead of panicking
+        let mut unfinished = microledger.blocks[0].clone();
+        unfinished.block.digital_fingerprint = None;
+        microledger.blocks.push(unfinished);
+        assert!(matches!(
+            microledger.pre_terminate_block(&SealBundle::new()),
+            Err(Error::MissingFingerprintError)
+        ));
+
+        Ok(())
+    }
 }